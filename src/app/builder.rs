@@ -1,5 +1,12 @@
 /// Module with functions to build fltk widgets
 
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    env,
+    rc::Rc
+};
+
 use fltk::{
     app::{
         App as FLTKApp,
@@ -9,13 +16,16 @@ use fltk::{
         event as get_last_event,
         MouseWheel
     },
+    browser::CheckBrowser,
     button::{
         Button,
-        CheckButton
+        CheckButton,
+        ToggleButton
     },
     draw,
     enums::{
         Align,
+        CallbackTrigger,
         Color,
         Event,
         FrameType
@@ -23,9 +33,17 @@ use fltk::{
     frame::Frame,
     group::{
         Pack,
-        PackType
+        PackType,
+        Scroll,
+        ScrollType
     },
     image,
+    input::{
+        Input,
+        IntInput,
+        SecretInput
+    },
+    menu::Choice,
     text::{
         TextBuffer,
         TextDisplay,
@@ -39,7 +57,9 @@ use fltk::{
         DisplayExt,
         ButtonExt,
         ValuatorExt,
-        ImageExt
+        ImageExt,
+        InputExt,
+        MenuExt
     },
     misc::Progress,
     valuator::Slider,
@@ -49,8 +69,11 @@ use fltk::{
     }
 };
 
-use crate::static_data;
-use super::{styles::*, state::ThreadSafeState, Message};
+use crate::{
+    config::{InstallerConfig, NetworkTimeouts, ProxyConfig, ProxyKind},
+    static_data
+};
+use super::{styles::*, state::ThreadSafeState, dialog, Message};
 
 
 /// Builds a default fltk app
@@ -375,6 +398,48 @@ fn _build_mid_frame(label: &str) -> Frame {
 }
 
 
+/// Builds a frame with the info about the latest release
+/// NOTE: the frame doesn't get added to any window
+pub fn build_release_frame() -> Frame {
+    let mut frame = Frame::default()
+        .with_size(RELEASE_FRAME_WIDTH, RELEASE_FRAME_HEIGHT)
+        .with_pos(RELEASE_FRAME_XPOS, RELEASE_FRAME_YPOS);
+    frame.set_align(Align::Center | Align::Inside);
+    frame.set_label(RELEASE_FRAME_LOADING_LABEL);
+    frame.set_label_color(C_DDLC_PINK_DARK);
+    frame.set_label_size(RELEASE_FRAME_LABEL_SIZE);
+
+    return frame;
+}
+
+
+/// Builds a frame with the version of the latest release for the welcome window
+/// NOTE: the frame doesn't get added to any window
+pub fn build_version_frame() -> Frame {
+    let mut frame = Frame::default()
+        .with_size(VERSION_FRAME_WIDTH, VERSION_FRAME_HEIGHT)
+        .with_pos(VERSION_FRAME_XPOS, VERSION_FRAME_YPOS);
+    frame.set_align(Align::Center | Align::Inside);
+    frame.set_label(RELEASE_FRAME_LOADING_LABEL);
+    frame.set_label_color(C_DDLC_PINK_DARK);
+    frame.set_label_size(VERSION_FRAME_LABEL_SIZE);
+
+    return frame;
+}
+
+/// Builds a text display for the changelog of the latest release
+fn _build_changelog_txt(txt_buf: TextBuffer, style_buf: TextBuffer) -> TextDisplay {
+    let mut txt_disp = TextDisplay::default()
+        .with_size(CHANGELOG_DISP_WIDTH, CHANGELOG_DISP_HEIGHT)
+        .with_pos(CHANGELOG_DISP_XPOS, CHANGELOG_DISP_YPOS);
+    txt_disp.wrap_mode(WrapMode::AtBounds, 0);
+    txt_disp.set_buffer(txt_buf);
+    txt_disp.set_highlight_data(style_buf, CHANGELOG_STYLES.to_vec());
+
+    return txt_disp;
+}
+
+
 /// Builds a frame containing credits text
 fn _build_credits_frame() -> Frame {
     let mut frame = Frame::default()
@@ -441,13 +506,22 @@ fn _build_welcome_win_pack(sender: Sender<Message>, app_state: &ThreadSafeState)
 }
 
 /// Builds the welcome windows
-pub fn build_welcome_win(sender: Sender<Message>, app_state: &ThreadSafeState) -> DoubleWindow {
+pub fn build_welcome_win(
+    sender: Sender<Message>,
+    app_state: &ThreadSafeState,
+    version_frame: &Frame,
+    changelog_buf: TextBuffer,
+    changelog_style_buf: TextBuffer
+) -> DoubleWindow {
     let mut welcome_win = build_inner_win();
     welcome_win.show();
     welcome_win.begin();
 
     _build_top_frame(WELCOME_TOP_FRAME_LABEL);
-    _build_mid_frame(WELCOME_MID_FRAME_LABEL);
+    let mut mid_frame = _build_mid_frame(WELCOME_MID_FRAME_LABEL);
+    mid_frame.set_size(MID_FRAME_WIDTH, WELCOME_MID_FRAME_HEIGHT);
+    welcome_win.add(version_frame);
+    _build_changelog_txt(changelog_buf, changelog_style_buf);
 
     _build_welcome_win_pack(sender, app_state);
 
@@ -528,6 +602,17 @@ fn _build_abort_back_contn_pack(sender: Sender<Message>, app_state: &ThreadSafeS
     );
 }
 
+/// Builds a pack of 4 buttons
+/// Example: <Abort> <Volume>      <Back> <Continue>
+/// The continue button shows the install summary
+fn _build_abort_back_summary_pack(sender: Sender<Message>, app_state: &ThreadSafeState) {
+    _build_4but_pack(
+        sender,
+        app_state,
+        (BUT_CONTINUE_LABEL, Message::ShowSummary)
+    );
+}
+
 /// Builds a pack of 4 buttons
 /// Example: <Abort> <Volume>      <Back> <Install>
 fn _build_abort_back_inst_pack(sender: Sender<Message>, app_state: &ThreadSafeState) {
@@ -693,41 +778,104 @@ pub fn build_select_dir_win(sender: Sender<Message>, app_state: &ThreadSafeState
 }
 
 
-/// Builds the options window with various settings for installer
-pub fn build_options_win(sender: Sender<Message>, app_state: &ThreadSafeState, is_dlx_version: bool, install_spr: bool) -> DoubleWindow {
-    let options_win = build_inner_win();
-    options_win.begin();
+/// Builds a pack for the components check buttons
+/// NOTE: the pack doesn't get added to any window
+pub fn build_components_pack() -> Pack {
+    let mut pack = Pack::default()
+        .with_size(COMPONENTS_PACK_WIDTH, COMPONENTS_PACK_HEIGHT)
+        .with_pos(COMPONENTS_PACK_XPOS, COMPONENTS_PACK_YPOS)
+        .with_type(PackType::Vertical);
+    pack.set_spacing(BUT_SPACING);
 
+    pack.end();
 
-    _build_top_frame(OPTIONS_FRAME_LABEL);
+    return pack;
+}
+
+/// Fills the pack with check buttons for the components from the app state
+/// The old buttons are removed from the pack
+/// NOTE: we add the buttons explicitly, so this can be used at runtime
+/// without messing with the current group
+pub fn fill_components_pack(pack: &mut Pack, sender: Sender<Message>, app_state: &ThreadSafeState) -> Vec<CheckButton> {
+    pack.clear();
+
+    let app_state = app_state.lock().unwrap();
+    let mut buttons = Vec::new();
+    for (i, component) in app_state.get_components().iter().enumerate() {
+        let but = _build_check_button(
+            BUT_COMPONENT_CHECK_WIDTH,
+            BUT_COMPONENT_CHECK_HEIGHT,
+            &component.display_name,
+            sender,
+            Message::ComponentCheck(i),
+            app_state.is_component_selected(&component.id)
+        );
+        pack.add(&but);
+        buttons.push(but);
+    }
 
+    pack.redraw();
 
-    const TOTAL_BUTS: i32 = 2;
-    const XPOS: i32 = INNER_WIN_CONTENT_XPADDING;
-    const YPOS: i32 = INNER_WIN_HEIGHT/2 - TOTAL_BUTS*BUT_HEIGHT/2 - (TOTAL_BUTS-1)*BUT_SPACING/2;
-    const YPOS_INC: i32 = BUT_HEIGHT + BUT_SPACING;
+    return buttons;
+}
 
-    let mut but_inst_dlx = _build_check_button(
-        BUT_DLX_VER_CHECK_WIDTH,
-        BUT_DLX_VER_CHECK_HEIGHT,
-        BUT_DLX_VER_CHECK_LABEL,
+/// Builds a pack with the check buttons for the desktop entries
+fn _build_desktop_entry_pack(sender: Sender<Message>, app_state: &ThreadSafeState) -> Pack {
+    let mut pack = Pack::default()
+        .with_size(DESKTOP_ENTRY_PACK_WIDTH, BUT_DESKTOP_ENTRY_CHECK_HEIGHT)
+        .with_pos(DESKTOP_ENTRY_PACK_XPOS, DESKTOP_ENTRY_PACK_YPOS)
+        .with_type(PackType::Horizontal);
+    pack.set_spacing(BUT_SPACING);
+
+    let (menu_entry, desktop_shortcut) = app_state.lock().unwrap().get_desktop_entry_flags();
+    _build_check_button(
+        BUT_DESKTOP_ENTRY_CHECK_WIDTH,
+        BUT_DESKTOP_ENTRY_CHECK_HEIGHT,
+        BUT_MENU_ENTRY_LABEL,
         sender,
-        Message::DlxVersionCheck,
-        is_dlx_version
+        Message::MenuEntryCheck,
+        menu_entry
     );
-    but_inst_dlx.set_pos(XPOS, YPOS);
-    let mut but_inst_spr = _build_check_button(
-        BUT_INSTALL_SPR_CHECK_WIDTH,
-        BUT_INSTALL_SPR_CHECK_HEIGHT,
-        BUT_INSTALL_SPR_CHECK_LABEL,
+    _build_check_button(
+        BUT_DESKTOP_ENTRY_CHECK_WIDTH,
+        BUT_DESKTOP_ENTRY_CHECK_HEIGHT,
+        BUT_DESKTOP_SHORTCUT_LABEL,
         sender,
-        Message::InstallSprCheck,
-        install_spr
+        Message::DesktopShortcutCheck,
+        desktop_shortcut
     );
-    but_inst_spr.set_pos(XPOS, YPOS+YPOS_INC);
 
+    pack.end();
 
-    _build_abort_back_inst_pack(sender, app_state);
+    return pack;
+}
+
+/// Builds the options window with various settings for installer
+pub fn build_options_win(
+    sender: Sender<Message>,
+    app_state: &ThreadSafeState,
+    release_frame: &Frame,
+    components_pack: &Pack
+) -> DoubleWindow {
+    let mut options_win = build_inner_win();
+    options_win.begin();
+
+
+    _build_top_frame(OPTIONS_FRAME_LABEL);
+    options_win.add(release_frame);
+
+    options_win.add(components_pack);
+
+    // Desktop entries are a freedesktop thing
+    if env::consts::OS == "linux" {
+        _build_desktop_entry_pack(sender, app_state);
+    }
+
+    let mut but_adv = build_button(BUT_ADVANCED_LABEL, sender, Message::OpenAdvancedOptions);
+    but_adv.set_pos(BUT_ADVANCED_XPOS, BUT_ADVANCED_YPOS);
+
+
+    _build_abort_back_contn_pack(sender, app_state);
 
 
     options_win.end();
@@ -736,6 +884,74 @@ pub fn build_options_win(sender: Sender<Message>, app_state: &ThreadSafeState, i
 }
 
 
+/// Builds a browser with check boxes for the submods
+/// NOTE: the browser doesn't get added to any window
+pub fn build_submods_browser(sender: Sender<Message>) -> CheckBrowser {
+    let mut browser = CheckBrowser::default()
+        .with_size(SUBMODS_BROWSER_WIDTH, SUBMODS_BROWSER_HEIGHT)
+        .with_pos(SUBMODS_BROWSER_XPOS, SUBMODS_BROWSER_YPOS);
+    browser.set_text_size(SUBMODS_BROWSER_TXT_SIZE);
+    browser.set_selection_color(C_DDLC_PINK_DARK);
+    browser.set_trigger(CallbackTrigger::Changed);
+    browser.emit(sender, Message::SubmodsCheck);
+
+    return browser;
+}
+
+/// Builds the submods window
+pub fn build_submods_win(
+    sender: Sender<Message>,
+    app_state: &ThreadSafeState,
+    browser: &CheckBrowser,
+    desc_buf: TextBuffer
+) -> DoubleWindow {
+    let mut submods_win = build_inner_win();
+    submods_win.begin();
+
+
+    _build_top_frame(SUBMODS_FRAME_LABEL);
+
+    submods_win.add(browser);
+
+    let mut txt = TextDisplay::default()
+        .with_size(SUBMODS_DESC_WIDTH, SUBMODS_DESC_HEIGHT)
+        .with_pos(SUBMODS_DESC_XPOS, SUBMODS_DESC_YPOS);
+    txt.wrap_mode(WrapMode::AtBounds, 0);
+    txt.set_selection_color(C_DDLC_PINK_DARK);
+    txt.set_buffer(desc_buf);
+
+    _build_abort_back_summary_pack(sender, app_state);
+
+
+    submods_win.end();
+
+    return submods_win;
+}
+
+/// Builds the install summary window
+pub fn build_summary_win(sender: Sender<Message>, app_state: &ThreadSafeState, txt_buf: TextBuffer) -> DoubleWindow {
+    let summary_win = build_inner_win();
+    summary_win.begin();
+
+
+    _build_top_frame(SUMMARY_FRAME_LABEL);
+
+    let mut txt = TextDisplay::default()
+        .with_size(TXT_DISP_WIDTH, TXT_DISP_HEIGHT)
+        .with_pos(TXT_DISP_XPOS, TXT_DISP_YPOS);
+    txt.wrap_mode(WrapMode::AtBounds, 0);
+    txt.set_selection_color(C_DDLC_PINK_DARK);
+    txt.set_buffer(txt_buf);
+
+    _build_abort_back_inst_pack(sender, app_state);
+
+
+    summary_win.end();
+
+    return summary_win;
+}
+
+
 /// Builds a progress bar
 pub fn build_progress_bar() -> Progress {
     let mut bar = Progress::default()
@@ -750,19 +966,42 @@ pub fn build_progress_bar() -> Progress {
     return bar;
 }
 
+/// Builds the pause/resume button for the progress window
+pub fn build_pause_button(sender: Sender<Message>) -> Button {
+    return build_button(BUT_PAUSE_LABEL, sender, Message::TogglePause);
+}
+
+fn _build_progress_win_inner_pack() -> Pack {
+    let mut inner_pack = Pack::default()
+        .with_size(2*BUT_WIDTH + 2*BUT_SPACING + BUT_MUTE_WIDTH, BUT_HEIGHT)
+        .with_align(Align::Center)
+        .with_type(PackType::Horizontal);
+    inner_pack.set_spacing(BUT_SPACING);
+
+    inner_pack.end();
+
+    return inner_pack;
+}
+
 /// Builds the downloading/installing window
-pub fn build_propgress_win(sender: Sender<Message>, app_state: &ThreadSafeState, bar: &Progress) -> DoubleWindow {
+pub fn build_propgress_win(
+    sender: Sender<Message>,
+    app_state: &ThreadSafeState,
+    bar: &Progress,
+    pause_but: &Button
+) -> DoubleWindow {
     let mut progress_win = build_inner_win();
     progress_win.begin();
 
 
     _build_top_frame(PROGRESS_FRAME_LABEL);
 
-    let mut pack = _build_4but_left_inner_pack();
+    let mut pack = _build_progress_win_inner_pack();
     pack.set_pos(INNER_WIN_CONTENT_XPADDING, INNER_WIN_HEIGHT-BUT_HEIGHT-BUT_PACK_YPADDING);
     pack.begin();
 
     build_button(BUT_ABORT_LABEL, sender, Message::Abort);
+    pack.add(pause_but);
     build_volume_but(sender, app_state);
 
     pack.end();
@@ -780,7 +1019,7 @@ pub fn build_propgress_win(sender: Sender<Message>, app_state: &ThreadSafeState,
 /// text:
 ///     more text:
 ///         even more text
-fn __format_alert_msg(msg: &str) -> String {
+pub fn format_alert_msg(msg: &str) -> String {
     let mut rv = String::new();
     let mut n: usize = 1;
 
@@ -835,7 +1074,7 @@ pub fn build_alert_win(msg: &str) -> DoubleWindow {
 
     let mut buf = TextBuffer::default();
     buf.set_text(
-        &__format_alert_msg(msg)
+        &format_alert_msg(msg)
     );
 
     let mut txt = TextDisplay::default()
@@ -900,6 +1139,474 @@ pub fn build_msg_win(msg: &str) -> DoubleWindow {
 }
 
 
+/// Inputs of the advanced options window related to proxy
+#[derive(Clone)]
+struct AdvProxyInputs {
+    kind: Choice,
+    address: Input,
+    username: Input,
+    password: SecretInput
+}
+
+impl AdvProxyInputs {
+    /// Fills the inputs using the given proxy settings
+    fn set_proxy_config(&mut self, proxy: &Option<ProxyConfig>) {
+        match proxy {
+            Some(proxy) => {
+                let kind_id = ProxyKind::ALL.iter().position(|k| *k == proxy.kind).unwrap_or_default();
+                self.kind.set_value(kind_id as i32 + 1);
+                self.address.set_value(&proxy.address);
+                self.username.set_value(proxy.username.as_deref().unwrap_or_default());
+                self.password.set_value(proxy.password.as_deref().unwrap_or_default());
+            },
+            None => {
+                self.kind.set_value(0);
+            }
+        };
+    }
+
+    /// Reads proxy settings from the inputs
+    /// Returns an error message if the settings are invalid
+    fn get_proxy_config(&self) -> Result<Option<ProxyConfig>, &'static str> {
+        let kind_id = self.kind.value();
+        if kind_id < 1 {
+            return Ok(None);
+        }
+        let kind = ProxyKind::ALL[(kind_id - 1) as usize];
+
+        let address = self.address.value().trim().to_string();
+        if address.is_empty() {
+            return Err(INVALID_PROXY_MSG);
+        }
+
+        let username = self.username.value();
+        let password = self.password.value();
+        let (username, password) = match username.is_empty() {
+            true => (None, None),
+            false => (Some(username), Some(password))
+        };
+
+        return Ok(Some(ProxyConfig::new(kind, address, username, password)));
+    }
+}
+
+/// Sets common style of the advanced options inputs
+fn _style_adv_input<W: WidgetExt>(widget: &mut W) {
+    widget.set_label_size(ADV_INPUT_LABEL_SIZE);
+    widget.set_label_color(C_DDLC_PINK_DARK);
+    widget.set_selection_color(C_DDLC_PINK_DARK);
+    widget.set_align(Align::Left);
+}
+
+/// Builds an input for the advanced options window
+/// row is the 0-based row of the input in the window
+fn _build_adv_input<W: WidgetExt + Default>(row: i32, label: &str) -> W {
+    let mut widget = W::default()
+        .with_size(ADV_INPUT_WIDTH, ADV_INPUT_HEIGHT)
+        .with_pos(ADV_INPUT_XPOS, ADV_INPUT_YPOS + row*(ADV_INPUT_HEIGHT+ADV_INPUT_SPACING))
+        .with_label(label);
+    _style_adv_input(&mut widget);
+
+    return widget;
+}
+
+/// Builds proxy inputs for the advanced options window
+fn _build_adv_proxy_inputs(proxy: &Option<ProxyConfig>) -> AdvProxyInputs {
+    let mut kind: Choice = _build_adv_input(0, ADV_PROXY_KIND_LABEL);
+    kind.add_choice(ADV_PROXY_KIND_CHOICES);
+    kind.set_color(C_DDLC_WHITE_ACT);
+
+    let mut inputs = AdvProxyInputs {
+        kind,
+        address: _build_adv_input(1, ADV_PROXY_ADDRESS_LABEL),
+        username: _build_adv_input(2, ADV_PROXY_USER_LABEL),
+        password: _build_adv_input(3, ADV_PROXY_PASSWORD_LABEL)
+    };
+    inputs.set_proxy_config(proxy);
+
+    return inputs;
+}
+
+/// Inputs of the advanced options window related to downloads
+#[derive(Clone)]
+struct AdvNetworkInputs {
+    speed_limit: IntInput,
+    connect_timeout: IntInput,
+    read_timeout: IntInput,
+    request_timeout: IntInput,
+    stall_timeout: IntInput
+}
+
+impl AdvNetworkInputs {
+    /// Fills the inputs using the given config
+    fn set_config(&mut self, config: &InstallerConfig) {
+        let timeouts = &config.timeouts;
+        _set_adv_number(&mut self.speed_limit, config.download_speed_limit.unwrap_or_default());
+        _set_adv_number(&mut self.connect_timeout, timeouts.connect);
+        _set_adv_number(&mut self.read_timeout, timeouts.read);
+        _set_adv_number(&mut self.request_timeout, timeouts.request);
+        _set_adv_number(&mut self.stall_timeout, timeouts.stall);
+    }
+
+    /// Reads the settings from the inputs into the given config
+    /// Returns an error message if a value is invalid, the config isn't changed then
+    fn apply_to_config(&self, config: &mut InstallerConfig) -> Result<(), &'static str> {
+        let speed_limit = _get_adv_number(&self.speed_limit)?;
+        let timeouts = NetworkTimeouts {
+            connect: _get_adv_number(&self.connect_timeout)?,
+            read: _get_adv_number(&self.read_timeout)?,
+            request: _get_adv_number(&self.request_timeout)?,
+            stall: _get_adv_number(&self.stall_timeout)?
+        };
+
+        config.download_speed_limit = Some(speed_limit).filter(|v| *v > 0);
+        config.timeouts = timeouts;
+        return Ok(());
+    }
+}
+
+/// Builds a number input for the advanced options window
+fn _build_adv_number_input(row: i32, label: &str, tooltip: &str) -> IntInput {
+    let mut input: IntInput = _build_adv_input(row, label);
+    input.set_tooltip(tooltip);
+
+    return input;
+}
+
+/// Sets the value of a number input, 0 is shown as an empty input
+fn _set_adv_number(input: &mut IntInput, value: u64) {
+    match value {
+        0 => input.set_value(""),
+        value => input.set_value(&value.to_string())
+    };
+}
+
+/// Reads the value of a number input, empty means 0
+/// Returns an error message if the value is invalid
+fn _get_adv_number(input: &IntInput) -> Result<u64, &'static str> {
+    let value = input.value();
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(0);
+    }
+    return value.parse::<u64>().map_err(|_| INVALID_NUMBER_MSG);
+}
+
+/// Builds download inputs for the advanced options window, starting from the given row
+fn _build_adv_network_inputs(row: i32, config: &InstallerConfig) -> AdvNetworkInputs {
+    let mut inputs = AdvNetworkInputs {
+        speed_limit: _build_adv_number_input(row, ADV_SPEED_LIMIT_LABEL, ADV_SPEED_LIMIT_TOOLTIP),
+        connect_timeout: _build_adv_number_input(row + 1, ADV_CONNECT_TIMEOUT_LABEL, ADV_TIMEOUT_TOOLTIP),
+        read_timeout: _build_adv_number_input(row + 2, ADV_READ_TIMEOUT_LABEL, ADV_TIMEOUT_TOOLTIP),
+        request_timeout: _build_adv_number_input(row + 3, ADV_REQUEST_TIMEOUT_LABEL, ADV_REQUEST_TIMEOUT_TOOLTIP),
+        stall_timeout: _build_adv_number_input(row + 4, ADV_STALL_TIMEOUT_LABEL, ADV_STALL_TIMEOUT_TOOLTIP)
+    };
+    inputs.set_config(config);
+
+    return inputs;
+}
+
+/// Builds the advanced options window
+/// The changes are written into the app state when the user clicks the OK button
+pub fn build_advanced_win(app_state: &ThreadSafeState) -> DoubleWindow {
+    let (sw, sh) = screen_size();
+
+    let win_x = sw as i32/2 - ADV_WIN_WIDTH/2;
+    let win_y = sh as i32/2 - ADV_WIN_HEIGHT/2;
+
+    let mut adv_win = Window::default()
+        .with_size(ADV_WIN_WIDTH, ADV_WIN_HEIGHT)
+        .with_pos(win_x, win_y)
+        .with_label(ADV_WIN_TITLE);
+    adv_win.set_color(C_DDLC_PINK_IDLE);
+
+    let mut inner_win = Window::default()
+        .with_size(INNER_ADV_WIN_WIDTH, INNER_ADV_WIN_HEIGHT)
+        .with_pos(WIN_PADDING, WIN_PADDING);
+    inner_win.set_color(C_DDLC_WHITE_IDLE);
+
+
+    let (proxy_inputs, network_inputs) = {
+        let app_state = app_state.lock().unwrap();
+        let config = app_state.get_config();
+        (
+            _build_adv_proxy_inputs(&config.proxy),
+            _build_adv_network_inputs(ADV_PROXY_ROWS, config)
+        )
+    };
+
+    const BUT_YPOS: i32 = INNER_ADV_WIN_HEIGHT - BUT_HEIGHT - BUT_ADV_WIN_PADDING;
+
+    let mut test_but = _build_button_base(
+        BUT_WIDTH,
+        BUT_HEIGHT,
+        BUT_TEST_CONN_LABEL,
+        _handle_button,
+        _draw_button
+    );
+    test_but.set_pos(INNER_ADV_WIN_WIDTH/2 - BUT_WIDTH - BUT_SPACING, BUT_YPOS);
+    test_but.set_callback({
+        let app_state = app_state.clone();
+        let proxy_inputs = proxy_inputs.clone();
+        let network_inputs = network_inputs.clone();
+        move |b| {
+            // Test with the timeouts from the inputs too
+            let mut config = app_state.lock().unwrap().get_config().clone();
+            let rv = proxy_inputs.get_proxy_config()
+                .and_then(|proxy| {
+                    network_inputs.apply_to_config(&mut config)?;
+                    config.proxy = proxy;
+                    Ok(())
+                });
+            if let Err(msg) = rv {
+                dialog::run_msg_dlg(msg);
+                return;
+            }
+
+            b.set_label(BUT_TEST_CONN_RUNNING_LABEL);
+            b.deactivate();
+            dialog::run_test_connection_dlg(config);
+            b.set_label(BUT_TEST_CONN_LABEL);
+            b.activate();
+        }
+    });
+
+    let mut ok_but = _build_button_base(
+        BUT_WIDTH,
+        BUT_HEIGHT,
+        BUT_OK_LABEL,
+        _handle_button,
+        _draw_button
+    );
+    ok_but.set_pos(INNER_ADV_WIN_WIDTH/2 + BUT_SPACING, BUT_YPOS);
+    ok_but.set_callback({
+        let app_state = app_state.clone();
+        let mut win = adv_win.clone();
+        move |_| {
+            let mut config = app_state.lock().unwrap().get_config().clone();
+            let rv = proxy_inputs.get_proxy_config()
+                .and_then(|proxy| {
+                    network_inputs.apply_to_config(&mut config)?;
+                    config.proxy = proxy;
+                    Ok(())
+                });
+            match rv {
+                Ok(()) => {
+                    *app_state.lock().unwrap().get_config_mut() = config;
+                    win.hide();
+                },
+                Err(msg) => dialog::run_msg_dlg(msg)
+            };
+        }
+    });
+
+
+    inner_win.end();
+
+    adv_win.end();
+    adv_win.hide();
+    adv_win.make_modal(true);
+
+    return adv_win;
+}
+
+
+/// Builds a tile for the folders gallery
+fn _build_folder_tile(folder: &str, preview: Option<&[u8]>) -> ToggleButton {
+    let mut tile = ToggleButton::default()
+        .with_size(FOLDERS_TILE_WIDTH, FOLDERS_TILE_HEIGHT)
+        .with_label(folder);
+    tile.set_frame(FrameType::FlatBox);
+    tile.set_down_frame(FrameType::FlatBox);
+    tile.set_color(C_DDLC_WHITE_IDLE);
+    tile.set_selection_color(C_DDLC_PINK_IDLE);
+    tile.set_label_size(FOLDERS_TILE_TXT_SIZE);
+    tile.set_label_color(C_BLACK);
+    tile.set_align(Align::Inside | Align::Bottom | Align::Clip);
+    tile.set_tooltip(folder);
+    tile.visible_focus(false);
+
+    if let Some(data) = preview {
+        match image::PngImage::from_data(data) {
+            Ok(mut img) => {
+                img.scale(FOLDERS_PREVIEW_WIDTH, FOLDERS_PREVIEW_HEIGHT, true, true);
+                tile.set_image(Some(img));
+            },
+            Err(e) => eprintln!("Failed to load preview for '{folder}': {e}")
+        };
+    }
+
+    return tile;
+}
+
+/// Places the tiles matching the filter into a grid, hides the rest
+fn _layout_folder_tiles(scroll: &mut Scroll, tiles: &mut [ToggleButton], filter: &str) {
+    let filter = filter.trim().to_lowercase();
+    let columns = ((FOLDERS_GALLERY_WIDTH - FOLDERS_GALLERY_SCROLLBAR_SIZE) / (FOLDERS_TILE_WIDTH + BUT_SPACING)).max(1);
+
+    // Children positions are absolute, reset the scroll so we can calculate them
+    scroll.scroll_to(0, 0);
+
+    let mut i = 0;
+    for tile in tiles.iter_mut() {
+        let is_visible = filter.is_empty() || tile.label().to_lowercase().contains(&filter);
+        if !is_visible {
+            tile.hide();
+            continue;
+        }
+        tile.set_pos(
+            scroll.x() + BUT_SPACING + (i % columns)*(FOLDERS_TILE_WIDTH + BUT_SPACING),
+            scroll.y() + BUT_SPACING + (i / columns)*(FOLDERS_TILE_HEIGHT + BUT_SPACING)
+        );
+        tile.show();
+        i += 1;
+    }
+    scroll.redraw();
+}
+
+/// Builds a button for the folders selection window
+fn _build_folders_win_button(label: &str, xpos: i32) -> Button {
+    let mut but = _build_button_base(
+        BUT_WIDTH,
+        BUT_HEIGHT,
+        label,
+        _handle_button,
+        _draw_button
+    );
+    but.set_pos(xpos, INNER_FOLDERS_WIN_HEIGHT - BUT_HEIGHT - BUT_FOLDERS_WIN_PADDING);
+
+    return but;
+}
+
+/// Builds a window to let the user choose folders from the app state folder choices,
+/// the folders are shown as a gallery with their previews
+/// The choice is written into the app state when the window gets closed
+pub fn build_folders_win(title: &str, app_state: &ThreadSafeState) -> DoubleWindow {
+    let (sw, sh) = screen_size();
+
+    let win_x = sw as i32/2 - FOLDERS_WIN_WIDTH/2;
+    let win_y = sh as i32/2 - FOLDERS_WIN_HEIGHT/2;
+
+    let mut folders_win = Window::default()
+        .with_size(FOLDERS_WIN_WIDTH, FOLDERS_WIN_HEIGHT)
+        .with_pos(win_x, win_y)
+        .with_label(&format!("{FOLDERS_WIN_TITLE} - {title}"));
+    folders_win.set_color(C_DDLC_PINK_IDLE);
+
+    let mut inner_win = Window::default()
+        .with_size(INNER_FOLDERS_WIN_WIDTH, INNER_FOLDERS_WIN_HEIGHT)
+        .with_pos(WIN_PADDING, WIN_PADDING);
+    inner_win.set_color(C_DDLC_WHITE_IDLE);
+
+
+    let mut search = Input::default()
+        .with_size(FOLDERS_SEARCH_WIDTH, FOLDERS_SEARCH_HEIGHT)
+        .with_pos(FOLDERS_SEARCH_XPOS, FOLDERS_SEARCH_YPOS)
+        .with_label(FOLDERS_SEARCH_LABEL);
+    _style_adv_input(&mut search);
+    search.set_trigger(CallbackTrigger::Changed);
+
+    let mut scroll = Scroll::default()
+        .with_size(FOLDERS_GALLERY_WIDTH, FOLDERS_GALLERY_HEIGHT)
+        .with_pos(FOLDERS_GALLERY_XPOS, FOLDERS_GALLERY_YPOS);
+    scroll.set_frame(FrameType::FlatBox);
+    scroll.set_color(C_DDLC_WHITE_ACT);
+    scroll.set_type(ScrollType::Vertical);
+    scroll.set_scrollbar_size(FOLDERS_GALLERY_SCROLLBAR_SIZE);
+
+    let mut tiles: Vec<ToggleButton> = {
+        let app_state = app_state.lock().unwrap();
+        app_state.get_folder_choices()
+            .iter()
+            .map(|folder| _build_folder_tile(folder, app_state.get_folder_preview(folder)))
+            .collect()
+    };
+    // Everything is selected by default, the same as for the other components
+    for tile in tiles.iter_mut() {
+        tile.set_value(true);
+    }
+    scroll.end();
+
+    let tiles = Rc::new(RefCell::new(tiles));
+    _layout_folder_tiles(&mut scroll, &mut tiles.borrow_mut(), "");
+
+    search.set_callback({
+        let tiles = tiles.clone();
+        let mut scroll = scroll.clone();
+        move |s| _layout_folder_tiles(&mut scroll, &mut tiles.borrow_mut(), &s.value())
+    });
+
+    // Select all/none only affect the folders matching the search
+    const BUT_XPOS: i32 = BUT_FOLDERS_WIN_PADDING;
+    const BUT_XPOS_INC: i32 = BUT_WIDTH + BUT_SPACING;
+    for (i, select) in [true, false].into_iter().enumerate() {
+        let label = match select {
+            true => BUT_SELECT_ALL_LABEL,
+            false => BUT_SELECT_NONE_LABEL
+        };
+        let mut but = _build_folders_win_button(label, BUT_XPOS + i as i32*BUT_XPOS_INC);
+        but.set_callback({
+            let tiles = tiles.clone();
+            let mut scroll = scroll.clone();
+            move |_| {
+                for tile in tiles.borrow_mut().iter_mut().filter(|t| t.visible()) {
+                    tile.set_value(select);
+                }
+                scroll.redraw();
+            }
+        });
+    }
+
+    // Closing the window keeps the default and installs all folders,
+    // we must answer anyway so the installer thread doesn't wait forever
+    let on_close = {
+        let app_state = app_state.clone();
+        let tiles = tiles.clone();
+        move |win: &mut DoubleWindow| {
+            let chosen: HashSet<String> = tiles.borrow()
+                .iter()
+                .map(|t| t.label())
+                .collect();
+            app_state.lock().unwrap().set_chosen_folders(chosen);
+            win.hide();
+        }
+    };
+
+    let mut ok_but = _build_folders_win_button(
+        BUT_OK_LABEL,
+        INNER_FOLDERS_WIN_WIDTH - BUT_WIDTH - BUT_FOLDERS_WIN_PADDING
+    );
+    ok_but.set_callback({
+        let app_state = app_state.clone();
+        let tiles = tiles.clone();
+        let mut win = folders_win.clone();
+        move |_| {
+            let chosen: HashSet<String> = tiles.borrow()
+                .iter()
+                .filter(|t| t.value())
+                .map(|t| t.label())
+                .collect();
+            if chosen.is_empty() {
+                dialog::run_msg_dlg(NO_FOLDERS_MSG);
+                return;
+            }
+            app_state.lock().unwrap().set_chosen_folders(chosen);
+            win.hide();
+        }
+    });
+
+
+    inner_win.end();
+
+    folders_win.end();
+    folders_win.set_callback(on_close);
+    folders_win.hide();
+    folders_win.make_modal(true);
+
+    return folders_win;
+}
+
+
 /// Builds a pack for the end screens
 fn _build_end_but_pack(sender: Sender<Message>) -> Pack {
     let mut pack = Pack::default()
@@ -913,6 +1620,7 @@ fn _build_end_but_pack(sender: Sender<Message>) -> Pack {
     let mut credits_but = build_button(BUT_CREDITS_LABEL, sender, Message::OpenCredits);
     credits_but.set_label_size(11);
     build_button(BUT_CHANGELOG_LABEL, sender, Message::OpenChangelog);
+    build_button(BUT_LAUNCH_GAME_LABEL, sender, Message::LaunchGame);
 
     pack.end();
 
@@ -926,13 +1634,36 @@ fn _build_exit_button(sender: Sender<Message>) -> Button {
     return but;
 }
 
+/// Builds the frame with the abort message
+/// NOTE: the frame doesn't get added to any window
+pub fn build_abort_frame() -> Frame {
+    return _build_mid_frame(ABORT_MID_FRAME_LABEL);
+}
+
+/// Builds the pack with the keep/roll back buttons, hidden by default
+/// NOTE: the pack doesn't get added to any window
+pub fn build_rollback_pack(sender: Sender<Message>) -> Pack {
+    let mut pack = _build_4but_right_inner_pack();
+    pack.set_pos(INNER_WIN_CONTENT_XPADDING, INNER_WIN_HEIGHT-BUT_HEIGHT-BUT_PACK_YPADDING);
+    pack.begin();
+
+    build_button(BUT_KEEP_LABEL, sender, Message::KeepChanges);
+    build_button(BUT_ROLLBACK_LABEL, sender, Message::Rollback);
+
+    pack.end();
+    pack.hide();
+
+    return pack;
+}
+
 /// Builds the abort windows
-pub fn build_abort_win(sender: Sender<Message>) -> DoubleWindow {
-    let abort_win = build_inner_win();
+pub fn build_abort_win(sender: Sender<Message>, frame: &Frame, rollback_pack: &Pack) -> DoubleWindow {
+    let mut abort_win = build_inner_win();
     abort_win.begin();
 
     _build_top_frame(ABORT_TOP_FRAME_LABEL);
-    _build_mid_frame(ABORT_MID_FRAME_LABEL);
+    abort_win.add(frame);
+    abort_win.add(rollback_pack);
 
     _build_exit_button(sender);
 
@@ -941,6 +1672,37 @@ pub fn build_abort_win(sender: Sender<Message>) -> DoubleWindow {
     return abort_win;
 }
 
+/// Builds the error window
+/// The text buffer gets the error once the installation fails
+pub fn build_error_win(sender: Sender<Message>, txt_buf: TextBuffer) -> DoubleWindow {
+    let error_win = build_inner_win();
+    error_win.begin();
+
+    _build_top_frame(ERROR_TOP_FRAME_LABEL);
+
+    let mut txt = TextDisplay::default()
+        .with_size(TXT_DISP_WIDTH, TXT_DISP_HEIGHT)
+        .with_pos(TXT_DISP_XPOS, TXT_DISP_YPOS);
+    txt.wrap_mode(WrapMode::AtBounds, 0);
+    txt.set_selection_color(C_DDLC_PINK_DARK);
+    txt.set_buffer(txt_buf);
+
+    let mut pack = _build_4but_right_inner_pack();
+    pack.set_pos(INNER_WIN_CONTENT_XPADDING, INNER_WIN_HEIGHT-BUT_HEIGHT-BUT_PACK_YPADDING);
+    pack.begin();
+
+    build_button(BUT_RETRY_LABEL, sender, Message::Retry);
+    build_button(BUT_BACK_TO_OPTIONS_LABEL, sender, Message::BackToOptions);
+
+    pack.end();
+
+    _build_exit_button(sender);
+
+    error_win.end();
+
+    return error_win;
+}
+
 /// Builds the done windows
 pub fn build_done_win(sender: Sender<Message>) -> DoubleWindow {
     let done_win = build_inner_win();
@@ -1,9 +1,12 @@
 /// The module that implements various dialogs
 
-use std::path::PathBuf;
+use std::{
+    path::PathBuf,
+    thread
+};
 
 use fltk::{
-    app::wait,
+    app::{wait, wait_for},
     dialog::{
         NativeFileChooser,
         NativeFileChooserType
@@ -14,8 +17,16 @@ use fltk::{
     },
 };
 
-use crate::utils;
-use super::builder;
+use crate::{
+    config::InstallerConfig,
+    installer,
+    utils
+};
+use super::{
+    builder,
+    state::ThreadSafeState,
+    styles::{TEST_CONN_OK_MSG, TEST_CONN_FAILED_MSG}
+};
 
 
 /// Launches select directory dialog native to the target OS
@@ -61,3 +72,51 @@ pub fn run_msg_dlg(msg: &str) {
     }
     drop(win);
 }
+
+/// Launches advanced options dialog
+/// NOTE: modal
+pub fn run_advanced_dlg(app_state: &ThreadSafeState) {
+    let mut win = builder::build_advanced_win(
+        app_state
+    );
+    win.show();
+    while win.shown() {
+        wait();
+    }
+    drop(win);
+}
+
+/// Launches folders selection dialog
+/// The choice is written into the app state
+/// NOTE: modal
+pub fn run_folders_dlg(title: &str, app_state: &ThreadSafeState) {
+    let mut win = builder::build_folders_win(
+        title,
+        app_state
+    );
+    win.show();
+    while win.shown() {
+        wait();
+    }
+    drop(win);
+}
+
+/// Tries to access the release API with the given config in a separate thread,
+/// then shows the result to the user
+/// NOTE: blocks until the test is done, but keeps the UI responsive
+pub fn run_test_connection_dlg(config: InstallerConfig) {
+    let th_handle = thread::spawn(
+        move || installer::test_connection(&config)
+    );
+    while !th_handle.is_finished() {
+        if wait_for(0.05).is_err() {
+            break;
+        }
+    }
+
+    match th_handle.join() {
+        Ok(Ok(_)) => run_msg_dlg(TEST_CONN_OK_MSG),
+        Ok(Err(e)) => run_alert_dlg(&format!("{TEST_CONN_FAILED_MSG}: {e}")),
+        Err(e) => eprintln!("Failed to join test connection thread {:?}", e)
+    };
+}
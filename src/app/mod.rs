@@ -15,6 +15,10 @@ use fltk::{
         Sender,
         Receiver
     },
+    browser::CheckBrowser,
+    button::{Button, CheckButton},
+    frame::Frame,
+    group::Pack,
     text::TextBuffer,
     misc::Progress,
     prelude::{
@@ -26,33 +30,58 @@ use fltk::{
 use webbrowser;
 
 use state::{ThreadSafeState, build_thread_safe_state};
-use super::{audio, errors, installer, utils};
+use super::{audio, errors, installer, markdown, submods, summary, utils};
 use errors::InstallError;
 
 
+// Index of the options window in the linked windows
+const OPTIONS_WINDOW_ID: usize = 3;
+// Index of the submods window, it's skipped if there's no catalog
+const SUBMODS_WINDOW_ID: usize = 4;
+
+
 /// The message enum so different parts of the app can communicate
 #[derive(Clone, Copy)]
 pub enum Message {
     UpdateProgressBar(f64),
+    // The downloaded size when the total is unknown
+    PulseProgressBar(u64),
     Close,
     NextPage,
     PrevPage,
     SelectDir,
-    DlxVersionCheck,
-    InstallSprCheck,
+    ComponentCheck(usize),
+    MenuEntryCheck,
+    DesktopShortcutCheck,
     VolumeCheck,
+    ShowSummary,
     Install,
     Preparing,
-    Downloading,
-    Extracting,
-    DownloadingSpr,
-    ExtractingSpr,
+    Downloading(usize),
+    Extracting(usize),
+    Streaming(usize),
     CleaningUp,
     Error,
+    Retry,
+    BackToOptions,
     Abort,
+    KeepChanges,
+    Rollback,
+    TogglePause,
     Done,
     OpenCredits,
-    OpenChangelog
+    OpenChangelog,
+    LaunchGame,
+    OpenAdvancedOptions,
+    ReleaseDataLoaded,
+    ReleaseDataFailed,
+    ReleaseChanged,
+    SubmodsCatalogLoaded,
+    SubmodsCheck,
+    SelectFolders(usize),
+    DownloadingSubmod(usize),
+    ExtractingSubmod(usize),
+    RemovingSubmods
 }
 
 
@@ -71,11 +100,12 @@ pub struct InstallerApp {
     main_window: DoubleWindow,
     // The windows the user can switch
     // using the back & continue buttons
-    linked_windows: [DoubleWindow; 5],
+    linked_windows: [DoubleWindow; 7],
     // Current window id
     current_window_id: usize,
     // These windows need to be available directly
     abort_window: DoubleWindow,
+    error_window: DoubleWindow,
     done_window: DoubleWindow,
 
     // Audio manager, option because audio might not work
@@ -85,8 +115,21 @@ pub struct InstallerApp {
     installer_th_handle: Option<thread::JoinHandle<installer::InstallResult>>,
 
     // These need to be updated
+    abort_frame: Frame,
+    rollback_pack: Pack,
     path_txt_buf: TextBuffer,
-    progress_bar: Progress
+    error_txt_buf: TextBuffer,
+    progress_bar: Progress,
+    pause_button: Button,
+    release_frame: Frame,
+    version_frame: Frame,
+    changelog_buf: TextBuffer,
+    changelog_style_buf: TextBuffer,
+    components_pack: Pack,
+    component_buttons: Vec<CheckButton>,
+    submods_browser: CheckBrowser,
+    submods_desc_buf: TextBuffer,
+    summary_txt_buf: TextBuffer
 }
 
 impl InstallerApp {
@@ -97,25 +140,47 @@ impl InstallerApp {
         let state = build_thread_safe_state();
 
         let path_txt_buf = TextBuffer::default();
+        let error_txt_buf = TextBuffer::default();
         let progress_bar = builder::build_progress_bar();
+        let pause_button = builder::build_pause_button(sender);
+        let release_frame = builder::build_release_frame();
+        let version_frame = builder::build_version_frame();
+        // The style buffer must always match the text
+        let loading_label = markdown::render(styles::CHANGELOG_LOADING_LABEL);
+        let mut changelog_buf = TextBuffer::default();
+        changelog_buf.set_text(&loading_label.text);
+        let mut changelog_style_buf = TextBuffer::default();
+        changelog_style_buf.set_text(&loading_label.style);
+        let mut components_pack = builder::build_components_pack();
+        let component_buttons = builder::fill_components_pack(&mut components_pack, sender, &state);
+        let submods_browser = builder::build_submods_browser(sender);
+        let mut submods_desc_buf = TextBuffer::default();
+        submods_desc_buf.set_text(styles::SUBMODS_LOADING_LABEL);
+        let summary_txt_buf = TextBuffer::default();
 
         let main_window = builder::build_outer_win(sender, &state);
         main_window.begin();
 
-        let linked_windows = {
-            let s = state.lock().unwrap();
-            let is_dlx_version = s.get_deluxe_ver_flag();
-            let install_spr = s.get_install_spr_flag();
-            [
-                builder::build_welcome_win(sender, &state),
-                builder::build_license_win(sender, &state),
-                builder::build_select_dir_win(sender, &state, path_txt_buf.clone()),
-                builder::build_options_win(sender, &state, is_dlx_version, install_spr),
-                builder::build_propgress_win(sender, &state, &progress_bar)
-            ]
-        };
-
-        let abort_window = builder::build_abort_win(sender);
+        let linked_windows = [
+            builder::build_welcome_win(
+                sender,
+                &state,
+                &version_frame,
+                changelog_buf.clone(),
+                changelog_style_buf.clone()
+            ),
+            builder::build_license_win(sender, &state),
+            builder::build_select_dir_win(sender, &state, path_txt_buf.clone()),
+            builder::build_options_win(sender, &state, &release_frame, &components_pack),
+            builder::build_submods_win(sender, &state, &submods_browser, submods_desc_buf.clone()),
+            builder::build_summary_win(sender, &state, summary_txt_buf.clone()),
+            builder::build_propgress_win(sender, &state, &progress_bar, &pause_button)
+        ];
+
+        let abort_frame = builder::build_abort_frame();
+        let rollback_pack = builder::build_rollback_pack(sender);
+        let abort_window = builder::build_abort_win(sender, &abort_frame, &rollback_pack);
+        let error_window = builder::build_error_win(sender, error_txt_buf.clone());
         let done_window = builder::build_done_win(sender);
 
         main_window.end();
@@ -137,14 +202,32 @@ impl InstallerApp {
             linked_windows,
             current_window_id: 0,
             abort_window,
+            error_window,
             done_window,
             audio_manager,
             installer_th_handle: None,
+            abort_frame,
+            rollback_pack,
             path_txt_buf,
-            progress_bar
+            error_txt_buf,
+            progress_bar,
+            pause_button,
+            release_frame,
+            version_frame,
+            changelog_buf,
+            changelog_style_buf,
+            components_pack,
+            component_buttons,
+            submods_browser,
+            submods_desc_buf,
+            summary_txt_buf
         };
         // Imprortant to set the dir again to update the text disp buffer
         installer.set_extraction_dir(utils::get_cwd());
+        // Show the cached release right away, then check for a new one
+        installer.update_release_info();
+        installer::fetch_release_data_in_thread(installer.sender, &installer.state);
+        submods::fetch_catalog_in_thread(installer.sender, &installer.state);
 
         return installer;
     }
@@ -162,6 +245,11 @@ impl InstallerApp {
                     Message::UpdateProgressBar(val) => {
                         self.progress_bar.set_value(val);
                     },
+                    Message::PulseProgressBar(downloaded) => {
+                        // We can't show the progress, so the bar just fills up again and again
+                        let val = (downloaded % styles::PB_PULSE_SIZE) as f64 / styles::PB_PULSE_SIZE as f64;
+                        self.progress_bar.set_value(val);
+                    },
                     Message::Close => {
                         break;
                     },
@@ -177,23 +265,26 @@ impl InstallerApp {
                             dialog::run_msg_dlg("注意!\n选择的文件夹不是正确的DDLC文件夹!");
                         }
                         self.set_extraction_dir(selected_dir);
+                        self.update_submods_browser();
                     },
-                    Message::DlxVersionCheck => {
+                    Message::ComponentCheck(i) => {
                         let mut app_state = self.state.lock().unwrap();
-                        app_state.invert_deluxe_ver_flag();
-                        match app_state.get_deluxe_ver_flag() {
-                            true => println!("Using deluxe version..."),
-                            false => println!("Using standard version...")
-                        };
+                        if let Some(id) = app_state.get_components().get(i).map(|c| c.id.clone()) {
+                            app_state.invert_component_flag(&id);
+                            match app_state.is_component_selected(&id) {
+                                true => println!("Including '{id}'..."),
+                                false => println!("Excluding '{id}'...")
+                            };
+                        }
+                        drop(app_state);
+                        self.update_component_buttons();
+                    },
+                    Message::MenuEntryCheck => {
+                        self.state.lock().unwrap().invert_menu_entry_flag();
+                    },
+                    Message::DesktopShortcutCheck => {
+                        self.state.lock().unwrap().invert_desktop_shortcut_flag();
                     },
-                    Message::InstallSprCheck => {
-                        let mut app_state = self.state.lock().unwrap();
-                        app_state.invert_install_spr_flag();
-                        match app_state.get_install_spr_flag() {
-                            true => println!("Including spritepacks..."),
-                            false => println!("Excluding spritepacks...")
-                        };
-                    }
                     Message::VolumeCheck => {
                         if let Some(ref am) = self.audio_manager {
                             let mut app_state = self.state.lock().unwrap();
@@ -211,44 +302,81 @@ impl InstallerApp {
                             self.redraw_current_window();
                         }
                     }
+                    Message::ShowSummary => {
+                        let app_state = self.state.lock().unwrap();
+                        if app_state.get_selected_components().is_empty() {
+                            dialog::run_msg_dlg(styles::NO_COMPONENTS_MSG);
+                            continue;
+                        }
+                        let summary = summary::InstallSummary::new(&app_state);
+                        drop(app_state);
+                        self.summary_txt_buf.set_text(&summary.to_string());
+                        self.show_next_window();
+                    },
                     Message::Install => {
                         let app_state = self.state.lock().unwrap();
+                        if app_state.get_selected_components().is_empty() {
+                            dialog::run_msg_dlg(styles::NO_COMPONENTS_MSG);
+                            continue;
+                        }
                         // We warn the user again if the extraction dir looks wrong
                         if !utils::is_valid_ddlc_dir(app_state.get_extraction_dir()) {
                             dialog::run_msg_dlg("注意!\n安装目标文件夹不是DDLC的文件夹!");
                         }
                         // We also need to move to the next window
                         self.sender.send(Message::NextPage);
-                        // Consume any existing thread first
                         // Have to drop the old ref
                         drop(app_state);
-                        self.cleanup_th_handle();
-                        // Start a new thread
-                        self.installer_th_handle = Some(
-                            installer::install_game_in_thread(self.sender, &self.state)
-                        );
+                        self.start_installation();
                     },
                     Message::Preparing => {
+                        self.set_pause_enabled(false);
                         println!("Preparing...");
                         self.progress_bar.set_label("准备中...");
                     },
-                    Message::Downloading => {
-                        println!("Done!\nDownloading...");
-                        self.progress_bar.set_label("下载中...");
+                    Message::Downloading(i) => {
+                        let name = self.get_component_name(i);
+                        self.set_pause_enabled(true);
+                        println!("Done!\nDownloading {name}...");
+                        self.progress_bar.set_label(&format!("下载 {name}..."));
+                    },
+                    Message::Extracting(i) => {
+                        let name = self.get_component_name(i);
+                        self.set_pause_enabled(false);
+                        println!("Done!\nExtracting {name}...");
+                        self.progress_bar.set_label(&format!("解压 {name}..."));
+                    },
+                    Message::Streaming(i) => {
+                        let name = self.get_component_name(i);
+                        self.set_pause_enabled(false);
+                        println!("Done!\nDownloading and extracting {name}...");
+                        self.progress_bar.set_label(&format!("下载并解压 {name}..."));
                     },
-                    Message::Extracting => {
-                        println!("Done!\nExtracting...");
-                        self.progress_bar.set_label("解压中...");
+                    Message::SelectFolders(i) => {
+                        let name = self.get_component_name(i);
+                        self.set_pause_enabled(false);
+                        println!("Done!\nWaiting for the user to select {name} folders...");
+                        dialog::run_folders_dlg(&name, &self.state);
                     },
-                    Message::DownloadingSpr => {
-                        println!("Done!\nDownloading spritepacks...");
-                        self.progress_bar.set_label("下载精灵包...");
+                    Message::DownloadingSubmod(i) => {
+                        let name = self.get_submod_name(i);
+                        self.set_pause_enabled(true);
+                        println!("Done!\nDownloading submod {name}...");
+                        self.progress_bar.set_label(&format!("下载子模组 {name}..."));
                     },
-                    Message::ExtractingSpr => {
-                        println!("Done!\nExtracting spritepacks...");
-                        self.progress_bar.set_label("解压精灵包...");
+                    Message::ExtractingSubmod(i) => {
+                        let name = self.get_submod_name(i);
+                        self.set_pause_enabled(false);
+                        println!("Done!\nExtracting submod {name}...");
+                        self.progress_bar.set_label(&format!("解压子模组 {name}..."));
+                    },
+                    Message::RemovingSubmods => {
+                        self.set_pause_enabled(false);
+                        println!("Done!\nRemoving submods...");
+                        self.progress_bar.set_label("移除子模组...");
                     },
                     Message::CleaningUp => {
+                        self.set_pause_enabled(false);
                         println!("Done!\nCleaning up...");
                         self.progress_bar.set_label("清除缓存...");
                     },
@@ -257,19 +385,84 @@ impl InstallerApp {
                         self.abort_installation();
                         let rv = self.cleanup_th_handle();
                         // Show the error if we can
-                        if let Some(e) = rv {
-                            dialog::run_alert_dlg(&format!("{e}"));
+                        let msg = match rv {
+                            Some(e) => format!("{}\n\n{}", styles::ERROR_HINT_LABEL, builder::format_alert_msg(&format!("{e}"))),
+                            None => styles::ERROR_HINT_LABEL.to_string()
+                        };
+                        self.error_txt_buf.set_text(&msg);
+                        self.hide_current_window();
+                        self.error_window.show();
+                    },
+                    Message::Retry => {
+                        println!("Retrying the installation...");
+                        self.error_window.hide();
+                        self.show_current_window();
+                        self.start_installation();
+                    },
+                    Message::BackToOptions => {
+                        println!("Going back to the options...");
+                        let mut app_state = self.state.lock().unwrap();
+                        app_state.reset_cancel_token();
+                        // The user can change the options, so we'll redo all the steps,
+                        // but the downloads can still be used
+                        if let Some(session) = app_state.get_install_session_mut() {
+                            session.reset_steps();
                         }
-                        // Let's just quit
-                        self.sender.send(Message::Close);
+                        drop(app_state);
+                        self.error_window.hide();
+                        self.change_window(OPTIONS_WINDOW_ID);
                     },
                     Message::Abort => {
                         println!("Installation has been aborted!");
                         self.abort_installation();
                         self.cleanup_th_handle();
+                        // Let the user choose what to do with the changes, if we've done any
+                        let has_changes = self.state.lock().unwrap()
+                            .get_install_session()
+                            .map(|s| s.get_journal().has_changes())
+                            .unwrap_or(false);
+                        if has_changes {
+                            self.abort_frame.set_label(styles::ABORT_ROLLBACK_MID_FRAME_LABEL);
+                            self.rollback_pack.show();
+                        }
                         self.hide_current_window();
                         self.abort_window.show();
                     },
+                    Message::KeepChanges => {
+                        println!("Keeping the installed files...");
+                        // This also removes the backups
+                        self.state.lock().unwrap().set_install_session(None);
+                        self.abort_frame.set_label(styles::ABORT_MID_FRAME_LABEL);
+                        self.rollback_pack.hide();
+                        self.abort_window.redraw();
+                    },
+                    Message::Rollback => {
+                        println!("Rolling back the installation...");
+                        let session = self.state.lock().unwrap().take_install_session();
+                        if let Some(session) = session {
+                            let failed_paths = session.get_journal().rollback();
+                            if !failed_paths.is_empty() {
+                                let paths: Vec<String> = failed_paths.iter()
+                                    .map(|p| p.display().to_string())
+                                    .collect();
+                                dialog::run_alert_dlg(&format!("{}:\n{}", styles::ROLLBACK_FAILED_MSG, paths.join("\n")));
+                            }
+                        }
+                        self.abort_frame.set_label(styles::ABORT_ROLLED_BACK_MID_FRAME_LABEL);
+                        self.rollback_pack.hide();
+                        self.abort_window.redraw();
+                    },
+                    Message::TogglePause => {
+                        let is_paused = self.state.lock().unwrap().get_pause_token().toggle();
+                        if is_paused {
+                            println!("Downloading has been paused...");
+                            self.pause_button.set_label(styles::BUT_RESUME_LABEL);
+                        }
+                        else {
+                            println!("Downloading has been resumed...");
+                            self.pause_button.set_label(styles::BUT_PAUSE_LABEL);
+                        }
+                    },
                     Message::Done => {
                         println!("Done!\nInstallation is complete!");
                         self.abort_installation();
@@ -285,6 +478,56 @@ impl InstallerApp {
                         if let Err(e) = webbrowser::open(crate::CHANGELOG_URL) {
                             eprintln!("Failed to open browser {e}");
                         };
+                    },
+                    Message::LaunchGame => {
+                        let ddlc_dir = self.state.lock().unwrap().get_extraction_dir().clone();
+                        match utils::launch_game(&ddlc_dir) {
+                            Ok(_) => break,
+                            Err(e) => {
+                                eprintln!("Failed to launch the game: {e}");
+                                dialog::run_alert_dlg(&format!("{}:\n{e}", styles::LAUNCH_FAILED_MSG));
+                            }
+                        };
+                    },
+                    Message::ReleaseDataLoaded => {
+                        self.update_release_info();
+                        self.component_buttons = builder::fill_components_pack(
+                            &mut self.components_pack,
+                            self.sender,
+                            &self.state
+                        );
+                        // Compatible submods depend on the release
+                        self.update_submods_browser();
+                    },
+                    Message::ReleaseDataFailed => {
+                        self.on_release_data_failed();
+                    },
+                    Message::ReleaseChanged => {
+                        println!("The release has changed, going back to the options...");
+                        self.cleanup_th_handle();
+                        // The steps we've done were for the old choices
+                        if let Some(session) = self.state.lock().unwrap().get_install_session_mut() {
+                            session.reset_steps();
+                        }
+                        dialog::run_msg_dlg(styles::RELEASE_CHANGED_MSG);
+                        self.change_window(OPTIONS_WINDOW_ID);
+                    },
+                    Message::SubmodsCatalogLoaded => {
+                        let label = match self.state.lock().unwrap().get_submods_catalog() {
+                            Some(_) => styles::SUBMODS_HINT_LABEL,
+                            None => styles::SUBMODS_FAILED_LABEL
+                        };
+                        self.submods_desc_buf.set_text(label);
+                        self.update_submods_browser();
+                    },
+                    Message::SubmodsCheck => {
+                        self.on_submods_check();
+                    },
+                    Message::OpenAdvancedOptions => {
+                        dialog::run_advanced_dlg(&self.state);
+                        if let Some(ref proxy) = self.state.lock().unwrap().get_config().proxy {
+                            println!("Using proxy {}...", proxy.get_url());
+                        }
                     }
                 };
             }
@@ -309,7 +552,6 @@ impl InstallerApp {
     }
 
     /// Shows current window
-    #[allow(dead_code)]
     pub fn show_current_window(&mut self) {
         self.linked_windows[self.current_window_id].show();
     }
@@ -321,7 +563,11 @@ impl InstallerApp {
 
     /// Hides current and shows next (current id + 1) window
     pub fn show_next_window(&mut self) {
-        self.change_window(self.current_window_id + 1);
+        let mut new_id = self.current_window_id + 1;
+        if new_id == SUBMODS_WINDOW_ID && !self.has_submods_catalog() {
+            new_id += 1;
+        }
+        self.change_window(new_id);
     }
 
     /// Hides current and shows previous (current id - 1) window
@@ -330,7 +576,16 @@ impl InstallerApp {
         if self.current_window_id == 0 {
             return;
         }
-        self.change_window(self.current_window_id - 1);
+        let mut new_id = self.current_window_id - 1;
+        if new_id == SUBMODS_WINDOW_ID && !self.has_submods_catalog() {
+            new_id -= 1;
+        }
+        self.change_window(new_id);
+    }
+
+    /// Checks if we have the submods catalog URL, otherwise there's nothing to show on the submods page
+    fn has_submods_catalog(&self) -> bool {
+        return self.state.lock().unwrap().get_config().get_submods_catalog_url().is_some();
     }
 
     /// Joins the installer thread handle
@@ -354,11 +609,140 @@ impl InstallerApp {
         return None;
     }
 
-    /// Aborts installation process by setting the flag
+    /// Starts the installer thread with a fresh token
+    fn start_installation(&mut self) {
+        // Consume any existing thread first
+        self.cleanup_th_handle();
+
+        let mut app_state = self.state.lock().unwrap();
+        app_state.reset_cancel_token();
+        drop(app_state);
+        self.set_pause_enabled(false);
+
+        // Start a new thread
+        self.installer_th_handle = Some(
+            installer::install_game_in_thread(self.sender, &self.state)
+        );
+    }
+
+    /// Activates the pause button while we download, only the downloads can be paused
+    /// Deactivating it also resumes the token, so it doesn't block the next download
+    fn set_pause_enabled(&mut self, value: bool) {
+        if value {
+            self.pause_button.activate();
+            return;
+        }
+        self.pause_button.deactivate();
+        self.state.lock().unwrap().get_pause_token().set_paused(false);
+        self.pause_button.set_label(styles::BUT_PAUSE_LABEL);
+    }
+
+    /// Aborts installation process by cancelling the token
     /// NOTE: DOES NOT JOIN THE THREAD
     fn abort_installation(&mut self) {
-        let mut app_state = self.state.lock().expect("Failed to lock app state mutex");
-        app_state.set_abort_flag(true);
+        let app_state = self.state.lock().expect("Failed to lock app state mutex");
+        app_state.get_cancel_token().cancel();
+    }
+
+    /// Returns display name of the component with the given index
+    fn get_component_name(&self, i: usize) -> String {
+        let app_state = self.state.lock().unwrap();
+        return match app_state.get_components().get(i) {
+            Some(component) => component.display_name.clone(),
+            None => String::new()
+        };
+    }
+
+    /// Updates the component check buttons using the selection from the app state
+    fn update_component_buttons(&mut self) {
+        let app_state = self.state.lock().unwrap();
+        for (but, component) in self.component_buttons.iter_mut().zip(app_state.get_components()) {
+            but.set_checked(app_state.is_component_selected(&component.id));
+            but.redraw();
+        }
+    }
+
+    /// Returns name of the submod with the given index in the catalog
+    fn get_submod_name(&self, i: usize) -> String {
+        let app_state = self.state.lock().unwrap();
+        return match app_state.get_submods_catalog().and_then(|c| c.submods.get(i)) {
+            Some(submod) => submod.name.clone(),
+            None => String::new()
+        };
+    }
+
+    /// Fills the submods browser using the catalog from the app state
+    fn update_submods_browser(&mut self) {
+        let app_state = self.state.lock().unwrap();
+        let installed_submods = app_state.get_installed_submods();
+
+        self.submods_browser.clear();
+        for submod in app_state.get_available_submods() {
+            let label = match installed_submods.get(&submod.id) {
+                Some(installed) => format!("{} [{} {}]", submod.name, styles::SUBMODS_INSTALLED_LABEL, installed.version),
+                None => submod.name.clone()
+            };
+            self.submods_browser.add(&label, app_state.get_selected_submods().contains(&submod.id));
+        }
+        self.submods_browser.redraw();
+    }
+
+    /// Saves the submods selection into the app state and shows the description
+    /// of the submod the user has clicked
+    fn on_submods_check(&mut self) {
+        let mut app_state = self.state.lock().unwrap();
+        let available_ids: Vec<String> = app_state.get_available_submods().iter().map(|s| s.id.clone()).collect();
+        for (i, id) in available_ids.iter().enumerate() {
+            // Browser items are 1-based
+            app_state.set_submod_selected(id, self.submods_browser.checked(i as i32 + 1));
+        }
+
+        let current_item = self.submods_browser.value();
+        if current_item < 1 {
+            return;
+        }
+        let submod = available_ids.get(current_item as usize - 1)
+            .and_then(|id| app_state.get_submods_catalog()?.get_submod(id));
+        if let Some(submod) = submod {
+            self.submods_desc_buf.set_text(
+                &format!("{}\n\n{}\n\n{}: {}", submod.name, submod.description, styles::SUBMODS_REPO_LABEL, submod.repo)
+            );
+        }
+    }
+
+    /// Updates the release frames and the changelog using the release data from the app state
+    fn update_release_info(&mut self) {
+        let app_state = self.state.lock().unwrap();
+        if let Some(data) = app_state.get_release_data() {
+            let label = format!("{}{} ({})", styles::RELEASE_FRAME_LABEL_PREFIX, data.get_version(), data.get_name());
+            self.release_frame.set_label(&label);
+            self.release_frame.redraw_label();
+            self.version_frame.set_label(&label);
+            self.version_frame.redraw_label();
+
+            let changelog = match data.get_changelog().trim() {
+                "" => markdown::render(styles::CHANGELOG_EMPTY_LABEL),
+                md => markdown::render(md)
+            };
+            self.changelog_buf.set_text(&changelog.text);
+            self.changelog_style_buf.set_text(&changelog.style);
+        }
+    }
+
+    /// Replaces the loading labels if we couldn't get the release data,
+    /// the cached release is kept as is
+    fn on_release_data_failed(&mut self) {
+        if self.state.lock().unwrap().get_release_data().is_some() {
+            return;
+        }
+        self.release_frame.set_label(styles::RELEASE_FRAME_FAILED_LABEL);
+        self.release_frame.redraw_label();
+        self.version_frame.set_label(styles::RELEASE_FRAME_FAILED_LABEL);
+        self.version_frame.redraw_label();
+
+        let changelog = markdown::render(styles::CHANGELOG_FAILED_LABEL);
+        self.changelog_buf.set_text(&changelog.text);
+        self.changelog_style_buf.set_text(&changelog.style);
     }
 
     /// Updates the extraction dir and text display with the path
@@ -1,13 +1,27 @@
 /// The module that implements AppState
 
 use std::{
+    collections::{HashMap, HashSet},
     path::PathBuf,
     sync::{
         Arc,
         Mutex
     },
 };
-use crate::audio::Volume;
+use crate::{
+    audio::Volume,
+    cancellation::{CancellationToken, PauseToken},
+    components::{
+        Component,
+        get_default_components,
+        get_default_selection,
+        get_game_dir
+    },
+    config::InstallerConfig,
+    installer::{ReleaseData, load_cached_release_data},
+    session::InstallSession,
+    submods::{SubmodInfo, SubmodsCatalog, SubmodsRecord}
+};
 
 
 pub type ThreadSafeState = Arc<Mutex<AppState>>;
@@ -17,70 +31,85 @@ pub type ThreadSafeState = Arc<Mutex<AppState>>;
 #[derive(Debug)]
 pub struct AppState {
     extraction_dir: PathBuf,
-    abort_flag: bool,
-    deluxe_ver_flag: bool,
-    install_spr_flag: bool,
-    music_volume: Volume
+    cancel_token: CancellationToken,
+    pause_token: PauseToken,
+    components: Vec<Component>,
+    selected_components: HashSet<String>,
+    music_volume: Volume,
+    config: InstallerConfig,
+    release_data: Option<ReleaseData>,
+    submods_catalog: Option<SubmodsCatalog>,
+    installed_submods: SubmodsRecord,
+    selected_submods: HashSet<String>,
+    // The folders the installer thread offers to the user and the user's answer
+    folder_choices: Vec<String>,
+    folder_previews: HashMap<String, Vec<u8>>,
+    chosen_folders: Option<HashSet<String>>,
+    // Linux only
+    create_menu_entry: bool,
+    create_desktop_shortcut: bool,
+    // The session of the failed installation we can retry or roll back
+    install_session: Option<InstallSession>
 }
 
 impl AppState {
-    /// Creates a new AppState
-    pub fn new(
-        extraction_dir: PathBuf,
-        abort_flag: bool,
-        deluxe_ver_flag: bool,
-        install_spr_flag: bool,
-        music_volume: Volume
-    ) -> Self {
-        return Self {
-            extraction_dir,
-            abort_flag,
-            deluxe_ver_flag,
-            install_spr_flag,
-            music_volume
-        };
-    }
-
     /// Returns the abort flag
     pub fn get_abort_flag(&self) -> bool {
-        return self.abort_flag;
+        return self.cancel_token.is_cancelled();
     }
 
-    /// Sets the abort flag
-    pub fn set_abort_flag(&mut self, value: bool) {
-        self.abort_flag = value;
+    /// Returns the token to cancel the installation,
+    /// it can be checked without locking the state
+    pub fn get_cancel_token(&self) -> CancellationToken {
+        return self.cancel_token.clone();
     }
 
-    /// Returns the dlx version flag
-    pub fn get_deluxe_ver_flag(&self) -> bool {
-        return self.deluxe_ver_flag;
+    /// Replaces the cancelled token with a new one so we can start the installation again
+    pub fn reset_cancel_token(&mut self) {
+        self.cancel_token = CancellationToken::new();
     }
 
-    /// Sets the dlx version flag
-    #[allow(dead_code)]
-    pub fn set_deluxe_ver_flag(&mut self, value: bool) {
-        self.deluxe_ver_flag = value;
+    /// Returns the token to pause the downloads,
+    /// it can be checked without locking the state
+    pub fn get_pause_token(&self) -> PauseToken {
+        return self.pause_token.clone();
     }
 
-    /// Inverts the dlx version flag
-    pub fn invert_deluxe_ver_flag(&mut self) {
-        self.deluxe_ver_flag = !self.deluxe_ver_flag;
+    /// Returns the components the user can choose from
+    pub fn get_components(&self) -> &[Component] {
+        return &self.components;
     }
 
-    /// Returns the install spritepacks flag
-    pub fn get_install_spr_flag(&self) -> bool {
-        return self.install_spr_flag;
+    /// Returns ids of the selected components
+    pub fn get_selected_components(&self) -> &HashSet<String> {
+        return &self.selected_components;
     }
 
-    /// Sets the install spritepacks flag
-    #[allow(dead_code)]
-    pub fn set_install_spr_flag(&mut self, value: bool) {
-        self.install_spr_flag = value;
+    /// Checks if the component with the given id is selected
+    pub fn is_component_selected(&self, id: &str) -> bool {
+        return self.selected_components.contains(id);
     }
 
-    /// Inverts the install spritepacks flag
-    pub fn invert_install_spr_flag(&mut self) {
-        self.install_spr_flag = !self.install_spr_flag;
+    /// Inverts the selection of the component with the given id
+    /// Selecting a component deselects the ones from the same groups,
+    /// required components can only be replaced by another one from their groups
+    pub fn invert_component_flag(&mut self, id: &str) {
+        let component = match self.components.iter().find(|c| c.id == id) {
+            Some(c) => c,
+            None => return
+        };
+        if self.selected_components.contains(id) {
+            if component.can_deselect() {
+                self.selected_components.remove(id);
+            }
+            return;
+        }
+        for other in self.components.iter() {
+            if component.is_exclusive_with(other) {
+                self.selected_components.remove(&other.id);
+            }
+        }
+        self.selected_components.insert(id.to_string());
     }
 
     /// Returns the extraction directory
@@ -94,8 +123,10 @@ impl AppState {
     }
 
     /// Sets the extraction directory
+    /// This also reloads the installed submods from the new directory
     pub fn set_extraction_dir(&mut self, new_path: PathBuf) {
         self.extraction_dir = new_path;
+        self.reload_installed_submods();
     }
 
     /// Returns the abort flag
@@ -107,17 +138,196 @@ impl AppState {
     pub fn set_music_volume(&mut self, value: Volume) {
         self.music_volume = value;
     }
+
+    /// Returns the installer config
+    pub fn get_config(&self) -> &InstallerConfig {
+        return &self.config;
+    }
+
+    /// Returns the installer config as mutable
+    pub fn get_config_mut(&mut self) -> &mut InstallerConfig {
+        return &mut self.config;
+    }
+
+    /// Returns the release data if we've got it
+    pub fn get_release_data(&self) -> Option<&ReleaseData> {
+        return self.release_data.as_ref();
+    }
+
+    /// Sets the release data
+    /// If the release comes with different components, resets the selection
+    pub fn set_release_data(&mut self, value: Option<ReleaseData>) {
+        if let Some(ref data) = value {
+            if data.get_components() != self.components.as_slice() {
+                self.components = data.get_components().to_vec();
+                self.selected_components = get_default_selection(&self.components);
+            }
+        }
+        self.release_data = value;
+        // The compatible submods depend on the version we install
+        let available_ids = self.get_available_submod_ids();
+        self.selected_submods.retain(|id| available_ids.contains(id));
+    }
+
+    /// Returns the submods catalog if we've got it
+    pub fn get_submods_catalog(&self) -> Option<&SubmodsCatalog> {
+        return self.submods_catalog.as_ref();
+    }
+
+    /// Sets the submods catalog and resets the submods selection
+    pub fn set_submods_catalog(&mut self, value: Option<SubmodsCatalog>) {
+        self.submods_catalog = value;
+        self.reset_selected_submods();
+    }
+
+    /// Returns the submods from the catalog compatible with the release we install
+    pub fn get_available_submods(&self) -> Vec<&SubmodInfo> {
+        let mas_version = self.release_data.as_ref().map(|d| d.get_version());
+        return match self.submods_catalog {
+            Some(ref catalog) => catalog.submods.iter().filter(|s| s.is_compatible(mas_version)).collect(),
+            None => Vec::new()
+        };
+    }
+
+    /// Returns ids of the submods from get_available_submods
+    pub fn get_available_submod_ids(&self) -> HashSet<String> {
+        return self.get_available_submods().iter().map(|s| s.id.clone()).collect();
+    }
+
+    /// Returns the record of the submods installed in the extraction directory
+    pub fn get_installed_submods(&self) -> &SubmodsRecord {
+        return &self.installed_submods;
+    }
+
+    /// Reloads the installed submods from the extraction directory
+    /// and resets the submods selection
+    pub fn reload_installed_submods(&mut self) {
+        self.installed_submods = SubmodsRecord::load(&get_game_dir(&self.extraction_dir));
+        self.reset_selected_submods();
+    }
+
+    /// Returns ids of the selected submods
+    pub fn get_selected_submods(&self) -> &HashSet<String> {
+        return &self.selected_submods;
+    }
+
+    /// Sets the selection of the submod with the given id
+    pub fn set_submod_selected(&mut self, id: &str, value: bool) {
+        match value {
+            true => self.selected_submods.insert(id.to_string()),
+            false => self.selected_submods.remove(id)
+        };
+    }
+
+    /// Returns the folders the user can choose from
+    pub fn get_folder_choices(&self) -> &[String] {
+        return &self.folder_choices;
+    }
+
+    /// Returns the PNG data of the folder preview, if any
+    pub fn get_folder_preview(&self, folder: &str) -> Option<&[u8]> {
+        return self.folder_previews.get(folder).map(|data| data.as_slice());
+    }
+
+    /// Sets the folders the user can choose from and their previews, clears the previous answer
+    pub fn set_folder_choices(&mut self, folders: Vec<String>, previews: HashMap<String, Vec<u8>>) {
+        self.folder_choices = folders;
+        self.folder_previews = previews;
+        self.chosen_folders = None;
+    }
+
+    /// Clears the folder choices and their previews
+    pub fn clear_folder_choices(&mut self) {
+        self.folder_choices.clear();
+        self.folder_previews.clear();
+    }
+
+    /// Sets the folders the user has chosen
+    pub fn set_chosen_folders(&mut self, value: HashSet<String>) {
+        self.chosen_folders = Some(value);
+    }
+
+    /// Takes the folders the user has chosen, None if the user hasn't answered yet
+    pub fn take_chosen_folders(&mut self) -> Option<HashSet<String>> {
+        return self.chosen_folders.take();
+    }
+
+    /// Returns if we create the app menu entry and the desktop shortcut
+    pub fn get_desktop_entry_flags(&self) -> (bool, bool) {
+        return (self.create_menu_entry, self.create_desktop_shortcut);
+    }
+
+    /// Inverts the app menu entry flag
+    pub fn invert_menu_entry_flag(&mut self) {
+        self.create_menu_entry = !self.create_menu_entry;
+    }
+
+    /// Inverts the desktop shortcut flag
+    pub fn invert_desktop_shortcut_flag(&mut self) {
+        self.create_desktop_shortcut = !self.create_desktop_shortcut;
+    }
+
+    /// Takes the session of the failed installation, if any
+    pub fn take_install_session(&mut self) -> Option<InstallSession> {
+        return self.install_session.take();
+    }
+
+    /// Sets the session of the failed installation
+    pub fn set_install_session(&mut self, value: Option<InstallSession>) {
+        self.install_session = value;
+    }
+
+    /// Returns the session of the failed installation, if any
+    pub fn get_install_session(&self) -> Option<&InstallSession> {
+        return self.install_session.as_ref();
+    }
+
+    /// Returns the session of the failed installation as mutable, if any
+    pub fn get_install_session_mut(&mut self) -> Option<&mut InstallSession> {
+        return self.install_session.as_mut();
+    }
+
+    /// Resets the submods selection so only the installed ones are selected
+    fn reset_selected_submods(&mut self) {
+        // The incompatible submods are hidden from the user, so we leave them alone
+        let available_ids = self.get_available_submod_ids();
+        self.selected_submods = self.installed_submods.submods.iter()
+            .filter(|s| available_ids.contains(&s.id))
+            .map(|s| s.id.clone())
+            .collect();
+    }
 }
 
 impl Default for AppState {
     fn default() -> Self {
-        return Self::new(
-            crate::utils::get_cwd(),
-            false,
-            true,
-            false,
-            1.0
-        );
+        let release_data = load_cached_release_data();
+        let components = match release_data {
+            Some(ref data) => data.get_components().to_vec(),
+            None => get_default_components()
+        };
+        let selected_components = get_default_selection(&components);
+        let extraction_dir = crate::utils::get_cwd();
+        let installed_submods = SubmodsRecord::load(&get_game_dir(&extraction_dir));
+
+        return Self {
+            extraction_dir,
+            cancel_token: CancellationToken::new(),
+            pause_token: PauseToken::new(),
+            components,
+            selected_components,
+            music_volume: 1.0,
+            config: crate::config::load_config(),
+            release_data,
+            submods_catalog: None,
+            installed_submods,
+            selected_submods: HashSet::new(),
+            folder_choices: Vec::new(),
+            folder_previews: HashMap::new(),
+            chosen_folders: None,
+            create_menu_entry: false,
+            create_desktop_shortcut: false,
+            install_session: None
+        };
     }
 }
 
@@ -7,7 +7,8 @@ use fltk::{
         Color,
         Font
     },
-    image::PngImage
+    image::PngImage,
+    text::StyleTableEntry
 };
 
 use lazy_static::lazy_static;
@@ -19,6 +20,10 @@ pub const WIN_TITLE: &str = "Monika After Story 安装器";
 pub const ALERT_WIN_TITLE: &str = "错误!";
 // Msg window title
 pub const MSG_WIN_TITLE: &str = "注意!";
+// Advanced options window title
+pub const ADV_WIN_TITLE: &str = "高级选项";
+// Folders selection window title
+pub const FOLDERS_WIN_TITLE: &str = "选择要安装的内容";
 
 
 // Window consts
@@ -42,6 +47,18 @@ pub const MSG_WIN_HEIGHT: i32 = ALERT_WIN_HEIGHT;
 pub const INNER_MSG_WIN_WIDTH: i32 = MSG_WIN_WIDTH - 2*WIN_PADDING;
 pub const INNER_MSG_WIN_HEIGHT: i32 = MSG_WIN_HEIGHT - 2*WIN_PADDING;
 
+pub const ADV_WIN_WIDTH: i32 = ALERT_WIN_WIDTH;
+pub const ADV_WIN_HEIGHT: i32 = 2*WIN_PADDING + ADV_INPUT_YPOS + ADV_ROWS*(ADV_INPUT_HEIGHT+ADV_INPUT_SPACING) + BUT_HEIGHT + 2*BUT_ADV_WIN_PADDING;
+
+pub const INNER_ADV_WIN_WIDTH: i32 = ADV_WIN_WIDTH - 2*WIN_PADDING;
+pub const INNER_ADV_WIN_HEIGHT: i32 = ADV_WIN_HEIGHT - 2*WIN_PADDING;
+
+pub const FOLDERS_WIN_WIDTH: i32 = 720;
+pub const FOLDERS_WIN_HEIGHT: i32 = 560;
+
+pub const INNER_FOLDERS_WIN_WIDTH: i32 = FOLDERS_WIN_WIDTH - 2*WIN_PADDING;
+pub const INNER_FOLDERS_WIN_HEIGHT: i32 = FOLDERS_WIN_HEIGHT - 2*WIN_PADDING;
+
 pub const INNER_WIN_CONTENT_XPADDING: i32 = 20;
 pub const INNER_WIN_CONTENT_YPADDING: i32 = INNER_WIN_CONTENT_XPADDING;
 
@@ -53,11 +70,14 @@ pub const BUT_HEIGHT: i32 = 35;
 pub const BUT_MUTE_WIDTH: i32 = BUT_HEIGHT;
 pub const BUT_MUTE_HEIGHT: i32 = BUT_MUTE_WIDTH;
 
-pub const BUT_DLX_VER_CHECK_WIDTH: i32 = BUT_WIDTH + 225;
-pub const BUT_DLX_VER_CHECK_HEIGHT: i32 = BUT_HEIGHT;
+pub const BUT_COMPONENT_CHECK_WIDTH: i32 = COMPONENTS_PACK_WIDTH;
+pub const BUT_COMPONENT_CHECK_HEIGHT: i32 = BUT_HEIGHT;
 
-pub const BUT_INSTALL_SPR_CHECK_WIDTH: i32 = BUT_WIDTH + 380;
-pub const BUT_INSTALL_SPR_CHECK_HEIGHT: i32 = BUT_HEIGHT;
+pub const BUT_ADVANCED_XPOS: i32 = INNER_WIN_CONTENT_XPADDING;
+pub const BUT_ADVANCED_YPOS: i32 = INNER_WIN_HEIGHT - BUT_PACK_YPADDING - 2*BUT_HEIGHT - 2*BUT_SPACING;
+
+pub const BUT_DESKTOP_ENTRY_CHECK_WIDTH: i32 = (COMPONENTS_PACK_WIDTH - BUT_SPACING) / 2;
+pub const BUT_DESKTOP_ENTRY_CHECK_HEIGHT: i32 = BUT_COMPONENT_CHECK_HEIGHT;
 
 // pub const BUT_CREDITS_WIDTH: i32 = BUT_WIDTH;
 // pub const BUT_CREDITS_HEIGHT: i32 = BUT_HEIGHT;
@@ -71,19 +91,33 @@ pub const BUT_FONT_SIZE: i32 = 16;
 pub const BUT_FONT: Font = Font::HelveticaBold;
 
 pub const BUT_ABORT_LABEL: &str = "取消";
+pub const BUT_PAUSE_LABEL: &str = "暂停";
+pub const BUT_RESUME_LABEL: &str = "继续";
+pub const BUT_RETRY_LABEL: &str = "重试";
+pub const BUT_BACK_TO_OPTIONS_LABEL: &str = "返回选项";
+pub const BUT_KEEP_LABEL: &str = "保留文件";
+pub const BUT_ROLLBACK_LABEL: &str = "回滚";
 pub const BUT_BACK_LABEL: &str = "@< 返回 ";
 pub const BUT_CONTINUE_LABEL: &str = " 继续@>";
 pub const BUT_SELECT_DIR_LABEL: &str = "浏览 @fileopen";
-pub const BUT_DLX_VER_CHECK_LABEL: &str = "豪华版 (预安装精灵包)";
-pub const BUT_INSTALL_SPR_CHECK_LABEL: &str = "下载精灵包 (另外下载到 '/spritepacks')";
 pub const BUT_INSTALL_LABEL: &str = "安装";
 pub const BUT_OK_LABEL: &str = "确认";
 pub const BUT_EXIT_LABEL: &str = "退出";
 pub const BUT_CREDITS_LABEL: &str = "MyNewSoundtrack\nYouTube 频道";
 pub const BUT_CHANGELOG_LABEL: &str = "更新日志";
+pub const BUT_LAUNCH_GAME_LABEL: &str = "启动游戏";
+pub const BUT_ADVANCED_LABEL: &str = "高级选项...";
+pub const BUT_MENU_ENTRY_LABEL: &str = "创建菜单快捷方式";
+pub const BUT_DESKTOP_SHORTCUT_LABEL: &str = "创建桌面快捷方式";
+pub const BUT_SELECT_ALL_LABEL: &str = "全选";
+pub const BUT_SELECT_NONE_LABEL: &str = "全不选";
+pub const BUT_TEST_CONN_LABEL: &str = "测试连接";
+pub const BUT_TEST_CONN_RUNNING_LABEL: &str = "测试中...";
 
 pub const BUT_ALERT_WIN_PADDING: i32 = 10;
 pub const BUT_MSG_WIN_PADDING: i32 = BUT_ALERT_WIN_PADDING;
+pub const BUT_ADV_WIN_PADDING: i32 = BUT_ALERT_WIN_PADDING;
+pub const BUT_FOLDERS_WIN_PADDING: i32 = BUT_ALERT_WIN_PADDING;
 pub const BUT_PACK_YPADDING: i32 = INNER_WIN_CONTENT_YPADDING;
 
 
@@ -102,6 +136,31 @@ pub const MID_FRAME_LABEL_SIZE: i32 = TOP_FRAME_LABEL_SIZE;
 
 pub const MSG_FRAME_LABEL_SIZE: i32 = LABEL_SIZE_MED;
 
+pub const RELEASE_FRAME_XPOS: i32 = TOP_FRAME_XPOS;
+pub const RELEASE_FRAME_YPOS: i32 = TOP_FRAME_YPOS + TOP_FRAME_HEIGHT;
+pub const RELEASE_FRAME_WIDTH: i32 = TOP_FRAME_WIDTH;
+pub const RELEASE_FRAME_HEIGHT: i32 = 25;
+pub const RELEASE_FRAME_LABEL_SIZE: i32 = 16;
+
+pub const WELCOME_MID_FRAME_HEIGHT: i32 = 70;
+
+pub const VERSION_FRAME_XPOS: i32 = TOP_FRAME_XPOS;
+pub const VERSION_FRAME_YPOS: i32 = MID_FRAME_YPOS + WELCOME_MID_FRAME_HEIGHT;
+pub const VERSION_FRAME_WIDTH: i32 = TOP_FRAME_WIDTH;
+pub const VERSION_FRAME_HEIGHT: i32 = 30;
+pub const VERSION_FRAME_LABEL_SIZE: i32 = LABEL_SIZE_MED;
+
+
+// Components pack consts
+pub const COMPONENTS_PACK_XPOS: i32 = INNER_WIN_CONTENT_XPADDING;
+pub const COMPONENTS_PACK_YPOS: i32 = RELEASE_FRAME_YPOS + RELEASE_FRAME_HEIGHT + 2*BUT_SPACING;
+pub const COMPONENTS_PACK_WIDTH: i32 = INNER_WIN_WIDTH - 2*INNER_WIN_CONTENT_XPADDING;
+pub const COMPONENTS_PACK_HEIGHT: i32 = DESKTOP_ENTRY_PACK_YPOS - COMPONENTS_PACK_YPOS - BUT_SPACING;
+
+pub const DESKTOP_ENTRY_PACK_XPOS: i32 = COMPONENTS_PACK_XPOS;
+pub const DESKTOP_ENTRY_PACK_YPOS: i32 = BUT_ADVANCED_YPOS - BUT_DESKTOP_ENTRY_CHECK_HEIGHT - BUT_SPACING;
+pub const DESKTOP_ENTRY_PACK_WIDTH: i32 = COMPONENTS_PACK_WIDTH;
+
 pub const CREDITS_FRAME_XPOS: i32 = 0;
 pub const CREDITS_FRAME_YPOS: i32 = INNER_WIN_HEIGHT - CREDITS_FRAME_HEIGHT;
 pub const CREDITS_FRAME_WIDTH: i32 = INNER_WIN_WIDTH;
@@ -115,6 +174,20 @@ pub const TXT_DISP_YPOS: i32 = MID_FRAME_YPOS;
 pub const TXT_DISP_WIDTH: i32 = INNER_WIN_WIDTH - 2*INNER_WIN_CONTENT_XPADDING;
 pub const TXT_DISP_HEIGHT: i32 = MID_FRAME_HEIGHT;
 
+pub const CHANGELOG_DISP_XPOS: i32 = TXT_DISP_XPOS;
+pub const CHANGELOG_DISP_YPOS: i32 = VERSION_FRAME_YPOS + VERSION_FRAME_HEIGHT + BUT_SPACING;
+pub const CHANGELOG_DISP_WIDTH: i32 = TXT_DISP_WIDTH;
+pub const CHANGELOG_DISP_HEIGHT: i32 = MID_FRAME_YPOS + MID_FRAME_HEIGHT - CHANGELOG_DISP_YPOS;
+pub const CHANGELOG_TXT_SIZE: i32 = 14;
+// Indexed by the style chars from the markdown module
+pub const CHANGELOG_STYLES: [StyleTableEntry; 5] = [
+    StyleTableEntry { color: C_BLACK, font: Font::Helvetica, size: CHANGELOG_TXT_SIZE },
+    StyleTableEntry { color: C_DDLC_PINK_DARK, font: Font::HelveticaBold, size: CHANGELOG_TXT_SIZE + 4 },
+    StyleTableEntry { color: C_BLACK, font: Font::HelveticaBold, size: CHANGELOG_TXT_SIZE },
+    StyleTableEntry { color: C_BLACK, font: Font::Courier, size: CHANGELOG_TXT_SIZE },
+    StyleTableEntry { color: C_DDLC_PINK_DARK, font: Font::Helvetica, size: CHANGELOG_TXT_SIZE }
+];
+
 
 // Text consts
 pub const SEL_DIR_TXT_XPOS: i32 = INNER_WIN_CONTENT_XPADDING;
@@ -123,15 +196,80 @@ pub const SEL_DIR_TXT_WIDTH: i32 = INNER_WIN_WIDTH - 2*INNER_WIN_CONTENT_XPADDIN
 pub const SEL_DIR_TXT_HEIGHT: i32 = 28;
 pub const SEL_DIR_TXT_SIZE: i32 = 18;
 
+// Advanced options inputs consts
+pub const ADV_INPUT_XPOS: i32 = 120;
+pub const ADV_INPUT_YPOS: i32 = INNER_WIN_CONTENT_YPADDING;
+pub const ADV_INPUT_WIDTH: i32 = INNER_ADV_WIN_WIDTH - ADV_INPUT_XPOS - INNER_WIN_CONTENT_XPADDING;
+pub const ADV_INPUT_HEIGHT: i32 = SEL_DIR_TXT_HEIGHT;
+pub const ADV_INPUT_SPACING: i32 = 12;
+pub const ADV_INPUT_LABEL_SIZE: i32 = 16;
+// Proxy kind, address, username, password
+pub const ADV_PROXY_ROWS: i32 = 4;
+// Speed limit and 4 timeouts
+pub const ADV_NETWORK_ROWS: i32 = 5;
+pub const ADV_ROWS: i32 = ADV_PROXY_ROWS + ADV_NETWORK_ROWS;
+
+pub const ADV_PROXY_KIND_LABEL: &str = "代理类型";
+pub const ADV_PROXY_KIND_CHOICES: &str = "无 (系统设置)|HTTP|HTTPS|SOCKS5|SOCKS5 (代理解析DNS)";
+pub const ADV_PROXY_ADDRESS_LABEL: &str = "代理地址";
+pub const ADV_PROXY_USER_LABEL: &str = "用户名";
+pub const ADV_PROXY_PASSWORD_LABEL: &str = "密码";
+pub const ADV_SPEED_LIMIT_LABEL: &str = "限速 (KB/s)";
+pub const ADV_SPEED_LIMIT_TOOLTIP: &str = "留空或 0 表示不限速";
+pub const ADV_CONNECT_TIMEOUT_LABEL: &str = "连接超时 (秒)";
+pub const ADV_READ_TIMEOUT_LABEL: &str = "读取超时 (秒)";
+pub const ADV_REQUEST_TIMEOUT_LABEL: &str = "请求超时 (秒)";
+pub const ADV_STALL_TIMEOUT_LABEL: &str = "卡顿超时 (秒)";
+pub const ADV_TIMEOUT_TOOLTIP: &str = "留空或 0 表示不限制";
+pub const ADV_REQUEST_TIMEOUT_TOOLTIP: &str = "下载的每个分块最多用时, 超时后会重试该分块\n留空或 0 表示不限制";
+pub const ADV_STALL_TIMEOUT_TOOLTIP: &str = "多久没有收到数据后重试当前分块\n留空或 0 表示不限制";
+
 pub const SEL_DIR_DLG_PROMPT: &str = "选择一个 Doki Doki Literature Club 文件夹";
 
 pub const LABEL_SIZE_LARGE: i32 = 28;
 pub const LABEL_SIZE_MED: i32 = 20;
 
 
+// Submods page consts
+pub const SUBMODS_BROWSER_XPOS: i32 = INNER_WIN_CONTENT_XPADDING;
+pub const SUBMODS_BROWSER_YPOS: i32 = MID_FRAME_YPOS;
+pub const SUBMODS_BROWSER_WIDTH: i32 = TXT_DISP_WIDTH;
+pub const SUBMODS_BROWSER_HEIGHT: i32 = 210;
+pub const SUBMODS_BROWSER_TXT_SIZE: i32 = 16;
+
+pub const SUBMODS_DESC_XPOS: i32 = SUBMODS_BROWSER_XPOS;
+pub const SUBMODS_DESC_YPOS: i32 = SUBMODS_BROWSER_YPOS + SUBMODS_BROWSER_HEIGHT + BUT_SPACING;
+pub const SUBMODS_DESC_WIDTH: i32 = SUBMODS_BROWSER_WIDTH;
+pub const SUBMODS_DESC_HEIGHT: i32 = MID_FRAME_YPOS + MID_FRAME_HEIGHT - SUBMODS_DESC_YPOS;
+
+
+// Folders selection window consts
+pub const FOLDERS_SEARCH_XPOS: i32 = 70;
+pub const FOLDERS_SEARCH_YPOS: i32 = BUT_FOLDERS_WIN_PADDING;
+pub const FOLDERS_SEARCH_WIDTH: i32 = INNER_FOLDERS_WIN_WIDTH - FOLDERS_SEARCH_XPOS - BUT_FOLDERS_WIN_PADDING;
+pub const FOLDERS_SEARCH_HEIGHT: i32 = SEL_DIR_TXT_HEIGHT;
+
+pub const FOLDERS_GALLERY_XPOS: i32 = BUT_FOLDERS_WIN_PADDING;
+pub const FOLDERS_GALLERY_YPOS: i32 = FOLDERS_SEARCH_YPOS + FOLDERS_SEARCH_HEIGHT + BUT_SPACING;
+pub const FOLDERS_GALLERY_WIDTH: i32 = INNER_FOLDERS_WIN_WIDTH - 2*BUT_FOLDERS_WIN_PADDING;
+pub const FOLDERS_GALLERY_HEIGHT: i32 = INNER_FOLDERS_WIN_HEIGHT - FOLDERS_GALLERY_YPOS - BUT_HEIGHT - 2*BUT_FOLDERS_WIN_PADDING;
+pub const FOLDERS_GALLERY_SCROLLBAR_SIZE: i32 = 16;
+
+pub const FOLDERS_TILE_WIDTH: i32 = 150;
+pub const FOLDERS_TILE_HEIGHT: i32 = 140;
+pub const FOLDERS_TILE_TXT_SIZE: i32 = 14;
+// Leave some space for the label
+pub const FOLDERS_PREVIEW_WIDTH: i32 = FOLDERS_TILE_WIDTH - 2*BUT_SPACING;
+pub const FOLDERS_PREVIEW_HEIGHT: i32 = FOLDERS_TILE_HEIGHT - 3*FOLDERS_TILE_TXT_SIZE;
+
+pub const FOLDERS_SEARCH_LABEL: &str = "搜索";
+
+
 // Progress bar consts
 pub const PB_WIDTH: i32 = INNER_WIN_WIDTH - 2*INNER_WIN_CONTENT_XPADDING;
 pub const PB_HEIGHT: i32 = BUT_HEIGHT;
+// The bar fills up once per this many bytes when the download size is unknown
+pub const PB_PULSE_SIZE: u64 = 4*1024*1024;
 
 
 // Slider consts
@@ -169,17 +307,53 @@ pub const WELCOME_MID_FRAME_LABEL: &str = concat!(
 pub const LICENSE_FRAME_LABEL: &str = "您需要同意我们的条款才能继续";
 pub const SELECT_DIR_FRAME_LABEL: &str = "选择一个 Doki Doki Literature Club 文件夹";
 pub const OPTIONS_FRAME_LABEL: &str = "设置其它安装项";
+pub const RELEASE_FRAME_LOADING_LABEL: &str = "正在获取最新版本信息...";
+pub const RELEASE_FRAME_FAILED_LABEL: &str = "无法获取最新版本信息";
+pub const RELEASE_FRAME_LABEL_PREFIX: &str = "最新版本: ";
+pub const CHANGELOG_LOADING_LABEL: &str = "正在获取更新日志...";
+pub const CHANGELOG_FAILED_LABEL: &str = "无法获取更新日志, 请检查网络或代理设置";
+pub const CHANGELOG_EMPTY_LABEL: &str = "这个版本没有更新日志";
+pub const SUBMODS_FRAME_LABEL: &str = "选择要安装的子模组 (可选)";
+pub const SUBMODS_LOADING_LABEL: &str = "正在加载子模组目录...";
+pub const SUBMODS_FAILED_LABEL: &str = "无法加载子模组目录, 你可以直接继续安装";
+pub const SUBMODS_HINT_LABEL: &str = "点击子模组查看详细信息.\n取消勾选已安装的子模组将会移除它.";
+pub const SUBMODS_INSTALLED_LABEL: &str = "已安装";
+pub const SUBMODS_REPO_LABEL: &str = "仓库";
+pub const SUMMARY_FRAME_LABEL: &str = "请确认安装信息";
 pub const PROGRESS_FRAME_LABEL: &str = "正在安装, 请稍后...";
 pub const ABORT_TOP_FRAME_LABEL: &str = "已取消";
 pub const ABORT_MID_FRAME_LABEL: &str = concat!(
     "安装已经取消.\n",
     "任何已被安装的文件将被保留"
 );
+pub const ABORT_ROLLBACK_MID_FRAME_LABEL: &str = concat!(
+    "安装已经取消.\n",
+    "你可以保留已被安装的文件,\n",
+    "或者回滚到安装前的状态"
+);
+pub const ABORT_ROLLED_BACK_MID_FRAME_LABEL: &str = concat!(
+    "安装已经取消.\n",
+    "文件夹已恢复到安装前的状态"
+);
+pub const ROLLBACK_FAILED_MSG: &str = "注意!\n以下文件无法恢复";
+pub const ERROR_TOP_FRAME_LABEL: &str = "安装失败";
+pub const ERROR_HINT_LABEL: &str = concat!(
+    "安装过程中发生了错误.\n",
+    "你可以重试失败的步骤 (已下载的文件将被保留), 返回修改选项, 或者退出."
+);
 pub const DONE_TOP_FRAME_LABEL: &str = "Finished";
 pub const DONE_MID_FRAME_LABEL: &str = concat!(
     "Monika After Story 已经成功安装在\n",
     "你的电脑上"
 );
+pub const LAUNCH_FAILED_MSG: &str = "无法启动游戏";
+pub const TEST_CONN_OK_MSG: &str = "连接成功!";
+pub const TEST_CONN_FAILED_MSG: &str = "连接失败";
+pub const NO_COMPONENTS_MSG: &str = "注意!\n请至少选择一个安装项!";
+pub const RELEASE_CHANGED_MSG: &str = "注意!\n发布了新版本, 安装项已更新!\n请重新检查你的选择.";
+pub const NO_FOLDERS_MSG: &str = "注意!\n请至少选择一个文件夹!";
+pub const INVALID_NUMBER_MSG: &str = "注意!\n限速和超时必须是非负整数!";
+pub const INVALID_PROXY_MSG: &str = "注意!\n代理地址不能为空!";
 pub const CREDITS_FRAME_LABEL: &str = "安装器主题 by MyNewSoundtrack";
 
 
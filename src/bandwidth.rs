@@ -0,0 +1,158 @@
+/// The module that implements the download speed limit
+/// We use a token bucket, the readers take tokens for the bytes they've actually read
+/// and wait once they've run out of them
+
+use std::{
+    io::{self, Read},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant}
+};
+
+use crate::cancellation::{CancellationToken, cancelled_error};
+
+
+// How long we can go at full speed after being idle
+const BURST_DURATION: f64 = 0.5;
+// How often we check the token while waiting
+const POLL_DURATION: Duration = Duration::from_millis(50);
+
+
+/// Token bucket, the tokens are bytes
+#[derive(Debug)]
+struct TokenBucket {
+    // Bytes per second
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant
+}
+
+impl TokenBucket {
+    /// Creates a new full bucket
+    fn new(rate: u64, now: Instant) -> Self {
+        let rate = rate as f64;
+        let capacity = rate * BURST_DURATION;
+        return Self { rate, capacity, tokens: capacity, last_refill: now };
+    }
+
+    /// Adds the tokens for the time since the last refill
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed*self.rate).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Takes the tokens for the bytes we've read, the bucket can go into debt
+    /// Returns how long we need to wait to pay it off
+    fn consume(&mut self, amount: u64, now: Instant) -> Duration {
+        self.refill(now);
+        self.tokens -= amount as f64;
+        if self.tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+        return Duration::from_secs_f64(-self.tokens / self.rate);
+    }
+}
+
+
+/// Limits the download speed, the clones share the same limit
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    // None if the speed is unlimited
+    bucket: Option<Arc<Mutex<TokenBucket>>>
+}
+
+impl RateLimiter {
+    /// Creates a new limiter, rate is in bytes per second
+    /// None or 0 means there's no limit
+    pub fn new(rate: Option<u64>) -> Self {
+        let bucket = rate
+            .filter(|rate| *rate > 0)
+            .map(|rate| Arc::new(Mutex::new(TokenBucket::new(rate, Instant::now()))));
+        return Self { bucket };
+    }
+
+    /// Takes the tokens for the bytes we've read
+    /// Returns how long the reader needs to wait
+    pub fn consume(&self, amount: u64) -> Duration {
+        return match self.bucket {
+            Some(ref bucket) => bucket.lock().unwrap().consume(amount, Instant::now()),
+            None => Duration::ZERO
+        };
+    }
+}
+
+
+/// Wraps a reader to keep it within the speed limit
+pub struct ThrottledReader<R: Read> {
+    inner: R,
+    limiter: RateLimiter,
+    token: CancellationToken
+}
+
+impl<R: Read> ThrottledReader<R> {
+    /// Creates a new reader
+    pub fn new(inner: R, limiter: &RateLimiter, token: &CancellationToken) -> Self {
+        return Self { inner, limiter: limiter.clone(), token: token.clone() };
+    }
+}
+
+impl<R: Read> Read for ThrottledReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+
+        // Wait in small steps, so we can stop once the token is cancelled
+        let wait_until = Instant::now() + self.limiter.consume(n as u64);
+        while let Some(remaining) = wait_until.checked_duration_since(Instant::now()) {
+            if remaining.is_zero() {
+                break;
+            }
+            if self.token.is_cancelled() {
+                return Err(cancelled_error());
+            }
+            thread::sleep(remaining.min(POLL_DURATION));
+        }
+
+        return Ok(n);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(1000, start);
+
+        // The burst is free
+        assert_eq!(bucket.consume(500, start), Duration::ZERO);
+        // Then we pay for every byte
+        assert_eq!(bucket.consume(250, start), Duration::from_millis(250));
+        // The debt is paid off after waiting
+        assert_eq!(bucket.consume(100, start + Duration::from_millis(250)), Duration::from_millis(100));
+        // Being idle doesn't give more than the burst
+        assert_eq!(bucket.consume(600, start + Duration::from_secs(60)), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_throttled_reader() {
+        let data = vec![7; 3000];
+        let limiter = RateLimiter::new(Some(4000));
+        let mut reader = ThrottledReader::new(io::Cursor::new(data.clone()), &limiter, &CancellationToken::new());
+
+        let start = Instant::now();
+        let mut read_data = Vec::new();
+        reader.read_to_end(&mut read_data).unwrap();
+        assert_eq!(read_data, data);
+        // 2000 bytes of the burst, then 1000 bytes at 4 KB/s
+        assert!(start.elapsed() >= Duration::from_millis(250));
+
+        // No limit
+        let limiter = RateLimiter::new(None);
+        assert_eq!(limiter.consume(u64::MAX), Duration::ZERO);
+    }
+}
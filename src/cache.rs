@@ -0,0 +1,57 @@
+/// The module that implements a simple on-disk cache for the installer
+/// Each entry is a JSON file in the user cache dir
+
+use std::{
+    fs,
+    path::PathBuf
+};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::utils;
+
+
+/// Returns the path to the cache file for the given entry
+fn get_entry_path(name: &str) -> Option<PathBuf> {
+    return Some(utils::get_cache_dir()?.join(format!("{name}.json")));
+}
+
+/// Loads an entry from the cache
+/// Returns None if there's no such entry or it's invalid
+pub fn load<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let path = get_entry_path(name)?;
+    let data = fs::read(&path).ok()?;
+    return match serde_json::from_slice(&data) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            eprintln!("Cache entry '{}' is invalid: {e}", path.display());
+            None
+        }
+    };
+}
+
+/// Saves an entry into the cache
+/// This function is "best-effort" and will ignore errors
+pub fn save<T: Serialize>(name: &str, value: &T) {
+    let path = match get_entry_path(name) {
+        Some(path) => path,
+        None => return
+    };
+    if let Some(parent_dir) = path.parent() {
+        if fs::create_dir_all(parent_dir).is_err() {
+            eprintln!("Failed to create cache dir '{}'", parent_dir.display());
+            return;
+        }
+    }
+
+    let data = match serde_json::to_vec(value) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("Failed to serialize cache entry '{name}': {e}");
+            return;
+        }
+    };
+    if let Err(e) = fs::write(&path, data) {
+        eprintln!("Failed to write cache entry '{}': {e}", path.display());
+    }
+}
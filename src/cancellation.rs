@@ -0,0 +1,389 @@
+/// The module that implements cancellation and pausing of the installation
+/// Blocking operations are either checked between reads,
+/// or moved into background threads so we can stop waiting for them
+
+use std::{
+    cmp::min,
+    io::{self, Read},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError}
+    },
+    thread,
+    time::{Duration, Instant}
+};
+
+
+// How often we check the token while waiting for a background operation
+const POLL_DURATION: Duration = Duration::from_millis(50);
+// Size of the chunks the background reader reads
+const READ_BUFFER_SIZE: usize = 64*1024;
+// Number of chunks the background reader can read ahead
+const READ_AHEAD_CHUNKS: usize = 4;
+
+
+/// Token to cancel the installation, the clones share the same state
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    flag: Arc<AtomicBool>
+}
+
+impl CancellationToken {
+    /// Creates a new token
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Cancels the token and all its clones
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    /// Checks if the token was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        return self.flag.load(Ordering::Relaxed);
+    }
+}
+
+
+/// Token to pause the downloads, the clones share the same state
+#[derive(Debug, Clone, Default)]
+pub struct PauseToken {
+    flag: Arc<AtomicBool>
+}
+
+impl PauseToken {
+    /// Creates a new token
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Pauses or resumes the token and all its clones
+    pub fn set_paused(&self, value: bool) {
+        self.flag.store(value, Ordering::Relaxed);
+    }
+
+    /// Inverts the token state, returns true if it's paused now
+    pub fn toggle(&self) -> bool {
+        return !self.flag.fetch_xor(true, Ordering::Relaxed);
+    }
+
+    /// Checks if the token is paused
+    pub fn is_paused(&self) -> bool {
+        return self.flag.load(Ordering::Relaxed);
+    }
+
+    /// Blocks the thread while the token is paused,
+    /// returns early if the cancellation token gets cancelled
+    pub fn wait(&self, cancel_token: &CancellationToken) {
+        while self.is_paused() && !cancel_token.is_cancelled() {
+            thread::sleep(POLL_DURATION);
+        }
+    }
+}
+
+
+/// Returns the error we use for cancelled operations
+pub fn cancelled_error() -> io::Error {
+    // NOTE: not Interrupted, io::copy would just retry
+    return io::Error::new(io::ErrorKind::Other, "cancelled by the user");
+}
+
+/// Runs blocking code in a background thread, so we can stop waiting for it
+/// once the token is cancelled. The code itself keeps running until it's done
+pub fn run_cancellable<T, F>(token: &CancellationToken, f: F) -> io::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static
+{
+    let (tx, rx) = mpsc::sync_channel(1);
+    thread::spawn(
+        move || {
+            // Nobody is waiting for the result if the token was cancelled
+            let _ = tx.send(f());
+        }
+    );
+
+    loop {
+        match rx.recv_timeout(POLL_DURATION) {
+            Ok(rv) => return Ok(rv),
+            Err(RecvTimeoutError::Timeout) => {
+                if token.is_cancelled() {
+                    return Err(cancelled_error());
+                }
+            },
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(io::Error::new(io::ErrorKind::Other, "background thread has panicked"));
+            }
+        };
+    }
+}
+
+
+/// Wraps a reader to stop reading once the token is cancelled
+/// NOTE: this only checks between reads, use AbortableReader for the reads that can stall
+pub struct CancellableReader<R: Read> {
+    inner: R,
+    token: CancellationToken
+}
+
+impl<R: Read> CancellableReader<R> {
+    /// Creates a new reader
+    pub fn new(inner: R, token: &CancellationToken) -> Self {
+        return Self { inner, token: token.clone() };
+    }
+}
+
+impl<R: Read> Read for CancellableReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.token.is_cancelled() {
+            return Err(cancelled_error());
+        }
+        return self.inner.read(buf);
+    }
+}
+
+
+/// Reader that reads from the inner reader in a background thread,
+/// the reads can be cancelled even if the inner reader has stalled (e.g. a network stream)
+/// Optionally the reads fail with TimedOut if the stream stalls or takes too long
+pub struct AbortableReader {
+    rx: Receiver<io::Result<Vec<u8>>>,
+    token: CancellationToken,
+    chunk: Vec<u8>,
+    chunk_pos: usize,
+    is_eof: bool,
+    stall_timeout: Option<Duration>,
+    deadline: Option<Instant>
+}
+
+impl AbortableReader {
+    /// Creates a new reader and starts reading from the inner one
+    pub fn new<R: Read + Send + 'static>(mut inner: R, token: &CancellationToken) -> Self {
+        let (tx, rx) = mpsc::sync_channel(READ_AHEAD_CHUNKS);
+        let th_token = token.clone();
+
+        thread::spawn(
+            move || {
+                loop {
+                    let mut chunk = vec![0; READ_BUFFER_SIZE];
+                    let rv = match inner.read(&mut chunk) {
+                        Ok(n) => {
+                            chunk.truncate(n);
+                            Ok(chunk)
+                        },
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                        Err(e) => Err(e)
+                    };
+                    let is_done = !matches!(rv, Ok(ref chunk) if !chunk.is_empty());
+                    // Stop on EOF, errors, or if the other side is gone
+                    if tx.send(rv).is_err() || is_done || th_token.is_cancelled() {
+                        break;
+                    }
+                }
+            }
+        );
+
+        return Self {
+            rx,
+            token: token.clone(),
+            chunk: Vec::new(),
+            chunk_pos: 0,
+            is_eof: false,
+            stall_timeout: None,
+            deadline: None
+        };
+    }
+
+    /// Sets the time without any data after which the reads fail
+    pub fn with_stall_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.stall_timeout = timeout;
+        return self;
+    }
+
+    /// Sets the instant after which the reads fail
+    pub fn with_deadline(mut self, deadline: Option<Instant>) -> Self {
+        self.deadline = deadline;
+        return self;
+    }
+
+    /// Returns an error if we've waited for the data for too long
+    fn check_stall(&self, wait_start: Instant) -> io::Result<()> {
+        if let Some(timeout) = self.stall_timeout {
+            if wait_start.elapsed() >= timeout {
+                return Err(
+                    io::Error::new(io::ErrorKind::TimedOut, format!("no data received for {} s", timeout.as_secs()))
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    /// Returns an error if we're past the deadline
+    fn check_deadline(&self) -> io::Result<()> {
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "the request took too long"));
+            }
+        }
+        return Ok(());
+    }
+}
+
+impl Read for AbortableReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // NOTE: we only count the time we wait, the caller might take its time between reads
+        let wait_start = Instant::now();
+        // A steady stream never waits for long, so check the deadline on every read
+        self.check_deadline()?;
+        while self.chunk_pos >= self.chunk.len() {
+            if self.is_eof {
+                return Ok(0);
+            }
+            if self.token.is_cancelled() {
+                return Err(cancelled_error());
+            }
+            match self.rx.recv_timeout(POLL_DURATION) {
+                Ok(Ok(chunk)) => {
+                    self.is_eof = chunk.is_empty();
+                    self.chunk = chunk;
+                    self.chunk_pos = 0;
+                },
+                Ok(Err(e)) => return Err(e),
+                Err(RecvTimeoutError::Timeout) => {
+                    self.check_stall(wait_start)?;
+                    self.check_deadline()?;
+                },
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::Error::new(io::ErrorKind::Other, "background reader has stopped"));
+                }
+            };
+        }
+
+        let n = min(buf.len(), self.chunk.len() - self.chunk_pos);
+        buf[..n].copy_from_slice(&self.chunk[self.chunk_pos..self.chunk_pos+n]);
+        self.chunk_pos += n;
+
+        return Ok(n);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reader that never returns
+    struct StalledReader;
+
+    impl Read for StalledReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(Duration::from_secs(60));
+            return Ok(0);
+        }
+    }
+
+    #[test]
+    fn test_abortable_reader_reads_everything() {
+        let data: Vec<u8> = (0..READ_BUFFER_SIZE*3 + 17).map(|i| i as u8).collect();
+        let mut reader = AbortableReader::new(io::Cursor::new(data.clone()), &CancellationToken::new());
+
+        let mut read_data = Vec::new();
+        reader.read_to_end(&mut read_data).unwrap();
+        assert_eq!(read_data, data);
+    }
+
+    #[test]
+    fn test_abortable_reader_cancels_stalled_read() {
+        let token = CancellationToken::new();
+        let mut reader = AbortableReader::new(StalledReader, &token);
+
+        let th_token = token.clone();
+        thread::spawn(
+            move || {
+                thread::sleep(Duration::from_millis(100));
+                th_token.cancel();
+            }
+        );
+
+        let start = Instant::now();
+        let rv = reader.read(&mut [0; 16]);
+        assert!(rv.is_err());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_abortable_reader_detects_stall() {
+        let mut reader = AbortableReader::new(StalledReader, &CancellationToken::new())
+            .with_stall_timeout(Some(Duration::from_millis(200)));
+
+        let start = Instant::now();
+        let err = reader.read(&mut [0; 16]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        let mut reader = AbortableReader::new(StalledReader, &CancellationToken::new())
+            .with_deadline(Some(Instant::now() + Duration::from_millis(100)));
+        assert_eq!(reader.read(&mut [0; 16]).unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
+    /// Produces a byte every 10 ms, never stalls and never ends
+    struct SlowReader;
+
+    impl Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(Duration::from_millis(10));
+            buf[0] = 1;
+            return Ok(1);
+        }
+    }
+
+    #[test]
+    fn test_abortable_reader_deadline_on_slow_stream() {
+        let mut reader = AbortableReader::new(SlowReader, &CancellationToken::new())
+            .with_stall_timeout(Some(Duration::from_secs(10)))
+            .with_deadline(Some(Instant::now() + Duration::from_millis(200)));
+
+        let start = Instant::now();
+        let err = io::copy(&mut reader, &mut io::sink()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_pause_token_wait() {
+        let pause_token = PauseToken::new();
+        assert!(pause_token.toggle());
+        assert!(!pause_token.toggle());
+
+        pause_token.set_paused(true);
+        let th_pause_token = pause_token.clone();
+        thread::spawn(
+            move || {
+                thread::sleep(Duration::from_millis(100));
+                th_pause_token.set_paused(false);
+            }
+        );
+        let start = Instant::now();
+        pause_token.wait(&CancellationToken::new());
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        // Cancelling stops the wait too
+        let cancel_token = CancellationToken::new();
+        cancel_token.cancel();
+        pause_token.set_paused(true);
+        pause_token.wait(&cancel_token);
+    }
+
+    #[test]
+    fn test_run_cancellable() {
+        let token = CancellationToken::new();
+        assert_eq!(run_cancellable(&token, || 42).unwrap(), 42);
+
+        token.cancel();
+        assert!(run_cancellable(&token, || thread::sleep(Duration::from_secs(60))).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+/// The module that implements adaptive chunk sizing for range requests
+/// We measure every range request and aim for chunks that take a few seconds:
+/// on slow or flaky links a failed chunk loses less, on fast links we send fewer requests
+
+use std::time::Duration;
+
+use crate::utils::format_size;
+
+
+pub const MIN_CHUNK_SIZE: u64 = 1024*1024;
+pub const MAX_CHUNK_SIZE: u64 = 64*1024*1024;
+const INITIAL_CHUNK_SIZE: u64 = 8*1024*1024;
+// The sizes are rounded to this
+const CHUNK_SIZE_STEP: u64 = 64*1024;
+// How long we want a chunk to take
+const TARGET_CHUNK_SECS: f64 = 4.0;
+// A chunk should take at least this many times the latency
+const MIN_LATENCY_MULTIPLE: f64 = 10.0;
+// Weight of the latest measurement in the average throughput
+const THROUGHPUT_SMOOTHING: f64 = 0.5;
+// The size changes at most this many times per request, so a single odd measurement doesn't throw it off
+const MAX_GROWTH: u64 = 2;
+// Transfers faster than this are too short to measure
+const MIN_MEASURED_SECS: f64 = 0.05;
+
+
+/// Picks the size of the next range request using the measurements of the previous ones
+#[derive(Debug, Clone)]
+pub struct ChunkSizer {
+    chunk_size: u64,
+    // Bytes per second, None until the first measurement
+    throughput: Option<f64>
+}
+
+impl ChunkSizer {
+    /// Creates a new sizer
+    pub fn new() -> Self {
+        return Self { chunk_size: INITIAL_CHUNK_SIZE, throughput: None };
+    }
+
+    /// Returns the size of the next chunk
+    pub fn get_chunk_size(&self) -> u64 {
+        return self.chunk_size;
+    }
+
+    /// Updates the size using the measurement of a range request
+    /// latency is the time until the response, transfer_time is the time we've read the body for
+    pub fn update(&mut self, requested: u64, received: u64, latency: Duration, transfer_time: Duration) {
+        // The server cut the response, the link is probably flaky
+        if received < requested {
+            self.shrink("short response");
+            return;
+        }
+
+        let secs = transfer_time.as_secs_f64().max(MIN_MEASURED_SECS);
+        let throughput = received as f64 / secs;
+        let throughput = match self.throughput {
+            Some(prev) => prev*(1.0 - THROUGHPUT_SMOOTHING) + throughput*THROUGHPUT_SMOOTHING,
+            None => throughput
+        };
+        self.throughput = Some(throughput);
+
+        let target_size = throughput * TARGET_CHUNK_SECS;
+        // Make the chunks large enough so we don't spend most of the time waiting for responses
+        let min_size_for_latency = throughput * latency.as_secs_f64() * MIN_LATENCY_MULTIPLE;
+        let new_size = (target_size.max(min_size_for_latency) as u64)
+            .clamp(self.chunk_size / MAX_GROWTH, self.chunk_size.saturating_mul(MAX_GROWTH));
+
+        self.set_chunk_size(
+            new_size,
+            &format!(
+                "throughput {}/s, latency {} ms",
+                format_size(throughput as u64),
+                latency.as_millis()
+            )
+        );
+    }
+
+    /// Shrinks the size after a failed request, so retries lose less
+    pub fn shrink(&mut self, reason: &str) {
+        self.set_chunk_size(self.chunk_size / 2, reason);
+    }
+
+    /// Sets the size within the limits and logs the change
+    fn set_chunk_size(&mut self, size: u64, reason: &str) {
+        let size = (size / CHUNK_SIZE_STEP * CHUNK_SIZE_STEP).clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE);
+        if size != self.chunk_size {
+            println!(
+                "Chunk size {} -> {} ({reason})",
+                format_size(self.chunk_size),
+                format_size(size)
+            );
+            self.chunk_size = size;
+        }
+    }
+}
+
+impl Default for ChunkSizer {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024*1024;
+
+    #[test]
+    fn test_grows_on_fast_links() {
+        let mut sizer = ChunkSizer::new();
+        // 8 MB in 0.1 s
+        sizer.update(8*MB, 8*MB, Duration::from_millis(20), Duration::from_millis(100));
+        assert_eq!(sizer.get_chunk_size(), 16*MB);
+
+        for _ in 0..10 {
+            let size = sizer.get_chunk_size();
+            sizer.update(size, size, Duration::from_millis(20), Duration::from_millis(100));
+        }
+        assert_eq!(sizer.get_chunk_size(), MAX_CHUNK_SIZE);
+    }
+
+    #[test]
+    fn test_shrinks_on_slow_links() {
+        let mut sizer = ChunkSizer::new();
+        // 8 MB in 32 s, 256 KB/s
+        sizer.update(8*MB, 8*MB, Duration::from_millis(100), Duration::from_secs(32));
+        assert_eq!(sizer.get_chunk_size(), 4*MB);
+        sizer.update(4*MB, 4*MB, Duration::from_millis(100), Duration::from_secs(16));
+        assert_eq!(sizer.get_chunk_size(), 2*MB);
+        sizer.update(2*MB, 2*MB, Duration::from_millis(100), Duration::from_secs(8));
+        assert_eq!(sizer.get_chunk_size(), MIN_CHUNK_SIZE);
+
+        // Short responses shrink it, but never below the min
+        sizer.update(MIN_CHUNK_SIZE, 10, Duration::ZERO, Duration::ZERO);
+        assert_eq!(sizer.get_chunk_size(), MIN_CHUNK_SIZE);
+    }
+
+    #[test]
+    fn test_high_latency_keeps_chunks_large() {
+        let mut sizer = ChunkSizer::new();
+        // 2 MB/s with 1 s latency would suggest 8 MB chunks, but we want 20 MB
+        sizer.update(8*MB, 8*MB, Duration::from_secs(1), Duration::from_secs(4));
+        assert_eq!(sizer.get_chunk_size(), 16*MB);
+        sizer.update(16*MB, 16*MB, Duration::from_secs(1), Duration::from_secs(8));
+        assert_eq!(sizer.get_chunk_size(), 20*MB);
+    }
+}
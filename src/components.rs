@@ -0,0 +1,172 @@
+/// The module that implements downloadable components of a release
+
+use std::{
+    collections::HashSet,
+    env,
+    path::{Component as PathComponent, Path, PathBuf}
+};
+
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+
+
+// Name of the release asset with the components manifest
+pub const MANIFEST_ASSET_NAME: &str = "installer_manifest.json";
+
+
+/// Represents a downloadable component of the release
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Component {
+    /// Unique id of the component
+    pub id: String,
+    /// The name we show to the user
+    pub display_name: String,
+    /// Regex pattern for the name of the release asset
+    pub asset_pattern: String,
+    /// Subpath to extract the component into,
+    /// relative to the game directory
+    #[serde(default)]
+    pub destination: String,
+    /// On macOS extract next to DDLC.app instead of inside it
+    #[serde(default)]
+    pub outside_app: bool,
+    /// Whether the component is selected by default
+    #[serde(default)]
+    pub default_on: bool,
+    /// Only one component in a group can be selected at a time
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Let the user choose which top-level folders of the archive to extract
+    #[serde(default)]
+    pub selective: bool,
+    /// One component from the groups of this component must stay selected,
+    /// so the groups work like radio buttons
+    #[serde(default)]
+    pub required: bool
+}
+
+impl Component {
+    /// Compiles the asset pattern of this component
+    pub fn get_asset_re(&self) -> Result<Regex, regex::Error> {
+        return Regex::new(&self.asset_pattern);
+    }
+
+    /// Check if this component is valid
+    pub fn is_valid(&self) -> bool {
+        // The destination must stay within the game dir
+        let is_dest_safe = Path::new(&self.destination)
+            .components()
+            .all(|c| matches!(c, PathComponent::Normal(_) | PathComponent::CurDir));
+
+        return {
+            !self.id.is_empty()
+            && !self.display_name.is_empty()
+            && self.get_asset_re().is_ok()
+            && is_dest_safe
+        };
+    }
+
+    /// Checks if the user can deselect this component
+    pub fn can_deselect(&self) -> bool {
+        return !self.required;
+    }
+
+    /// Checks if this component can't be selected together with the other one
+    pub fn is_exclusive_with(&self, other: &Component) -> bool {
+        return self.id != other.id && self.groups.iter().any(|g| other.groups.contains(g));
+    }
+
+    /// Returns the path to extract this component into
+    /// ddlc_dir is the dir the user selected
+    pub fn get_destination(&self, ddlc_dir: &Path) -> PathBuf {
+        // We don't want to put everything inside the app on mac
+        let base_dir = match self.outside_app && env::consts::OS == "macos" {
+            true => ddlc_dir.parent().unwrap_or(ddlc_dir).to_path_buf(),
+            false => get_game_dir(ddlc_dir)
+        };
+        return base_dir.join(&self.destination);
+    }
+}
+
+
+/// Represents a manifest with components, can be attached to a release
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ComponentManifest {
+    pub components: Vec<Component>
+}
+
+impl ComponentManifest {
+    /// Check if this manifest is valid
+    pub fn is_valid(&self) -> bool {
+        let mut ids = HashSet::new();
+        return {
+            !self.components.is_empty()
+            && self.components.iter().all(|c| c.is_valid() && ids.insert(c.id.as_str()))
+        };
+    }
+}
+
+
+/// Returns the path to the game files
+/// ddlc_dir is the dir the user selected
+pub fn get_game_dir(ddlc_dir: &Path) -> PathBuf {
+    // Since mac is pain, the game is within the app
+    if env::consts::OS == "macos" {
+        return ddlc_dir.join("Contents/Resources/autorun");
+    }
+    return ddlc_dir.to_path_buf();
+}
+
+/// Returns the components we use if the release has no manifest
+pub fn get_default_components() -> Vec<Component> {
+    return vec![
+        // We always install one of the versions of the mod
+        Component {
+            id: "def_ver".to_string(),
+            display_name: "标准版".to_string(),
+            asset_pattern: r"^Monika_After_Story-\d+\.\d+\.\d+-Mod\.zip$".to_string(),
+            destination: String::new(),
+            outside_app: false,
+            default_on: false,
+            groups: vec!["mas".to_string()],
+            selective: false,
+            required: true
+        },
+        Component {
+            id: "dlx_ver".to_string(),
+            display_name: "豪华版 (预安装精灵包)".to_string(),
+            asset_pattern: r"^Monika_After_Story-\d+\.\d+\.\d+-Mod-Dlx\.zip$".to_string(),
+            destination: String::new(),
+            outside_app: false,
+            default_on: true,
+            groups: vec!["mas".to_string()],
+            selective: false,
+            required: true
+        },
+        Component {
+            id: "spr".to_string(),
+            display_name: "精灵包 (另外下载到 '/spritepacks')".to_string(),
+            asset_pattern: r"^spritepacks\.zip$".to_string(),
+            destination: "spritepacks".to_string(),
+            outside_app: true,
+            default_on: false,
+            groups: Vec::new(),
+            selective: true,
+            required: false
+        }
+    ];
+}
+
+/// Returns ids of the components that are selected by default
+pub fn get_default_selection(components: &[Component]) -> HashSet<String> {
+    let mut selected: HashSet<String> = HashSet::new();
+    for component in components.iter().filter(|c| c.default_on) {
+        // Respect the groups even if the manifest is sloppy
+        let has_conflict = components.iter()
+            .any(|c| selected.contains(&c.id) && c.is_exclusive_with(component));
+        if !has_conflict {
+            selected.insert(component.id.clone());
+        }
+    }
+    return selected;
+}
@@ -0,0 +1,221 @@
+/// The module that implements desktop entries on Linux
+/// The entries follow the freedesktop spec, so the game shows up in the app menu
+
+use std::{
+    env,
+    fs,
+    io,
+    path::{Path, PathBuf}
+};
+
+use crate::{
+    rollback::FileJournal,
+    static_data::APP_ICON_DATA
+};
+
+
+const ENTRY_FILE_NAME: &str = "monika-after-story.desktop";
+const ICON_FILE_NAME: &str = "monika-after-story.png";
+const ENTRY_NAME: &str = "Monika After Story";
+const ENTRY_COMMENT: &str = "Doki Doki Literature Club! with Monika After Story";
+const LAUNCHER_NAME: &str = "DDLC.sh";
+
+
+/// Returns the home dir of the user
+fn get_home_dir() -> Option<PathBuf> {
+    return env::var_os("HOME").map(PathBuf::from).filter(|p| p.is_absolute());
+}
+
+/// Returns the XDG base dir from the env var or its default in the home dir
+fn get_xdg_dir(var: &str, default: &str) -> Option<PathBuf> {
+    return env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| get_home_dir().map(|p| p.join(default)));
+}
+
+/// Returns the dir with the user's desktop entries
+fn get_applications_dir() -> Option<PathBuf> {
+    return get_xdg_dir("XDG_DATA_HOME", ".local/share").map(|p| p.join("applications"));
+}
+
+/// Returns the path we write the icon into
+fn get_icon_path() -> Option<PathBuf> {
+    return get_xdg_dir("XDG_DATA_HOME", ".local/share").map(|p| p.join("icons").join(ICON_FILE_NAME));
+}
+
+/// Finds the desktop dir in the content of user-dirs.dirs
+fn parse_user_dirs(content: &str, home_dir: &Path) -> Option<PathBuf> {
+    for line in content.lines() {
+        let value = match line.trim().strip_prefix("XDG_DESKTOP_DIR=") {
+            Some(value) => value.trim().trim_matches('"'),
+            None => continue
+        };
+        let path = match value.strip_prefix("$HOME") {
+            Some(rest) => home_dir.join(rest.trim_start_matches('/')),
+            None => PathBuf::from(value)
+        };
+        return match path.is_absolute() {
+            true => Some(path),
+            false => None
+        };
+    }
+    return None;
+}
+
+/// Returns the desktop dir of the user, it's localized on some systems
+fn get_desktop_dir() -> Option<PathBuf> {
+    let home_dir = get_home_dir()?;
+    let desktop_dir = get_xdg_dir("XDG_CONFIG_HOME", ".config")
+        .and_then(|p| fs::read_to_string(p.join("user-dirs.dirs")).ok())
+        .and_then(|content| parse_user_dirs(&content, &home_dir))
+        .unwrap_or_else(|| home_dir.join("Desktop"));
+    return Some(desktop_dir);
+}
+
+/// Returns the paths of the entries we'd create, skips the dirs we couldn't find
+pub fn get_entry_paths(menu_entry: bool, desktop_shortcut: bool) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if menu_entry {
+        paths.extend(get_applications_dir().map(|p| p.join(ENTRY_FILE_NAME)));
+    }
+    if desktop_shortcut {
+        paths.extend(get_desktop_dir().map(|p| p.join(ENTRY_FILE_NAME)));
+    }
+    return paths;
+}
+
+
+/// Escapes a string value of the entry
+fn escape_value(value: &str) -> String {
+    let mut rv = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => rv.push_str("\\\\"),
+            '\n' => rv.push_str("\\n"),
+            '\t' => rv.push_str("\\t"),
+            '\r' => rv.push_str("\\r"),
+            _ => rv.push(c)
+        };
+    }
+    return rv;
+}
+
+/// Quotes an argument of the Exec key, the result still needs escaping as a string value
+fn quote_exec_arg(arg: &str) -> String {
+    let mut rv = String::from("\"");
+    for c in arg.chars() {
+        match c {
+            '"' | '`' | '$' | '\\' => {
+                rv.push('\\');
+                rv.push(c);
+            },
+            // Field codes start with it
+            '%' => rv.push_str("%%"),
+            _ => rv.push(c)
+        };
+    }
+    rv.push('"');
+    return rv;
+}
+
+/// Formats the desktop entry that launches the game from the given dir
+fn format_entry(ddlc_dir: &Path, icon_path: &Path) -> String {
+    let launcher = ddlc_dir.join(LAUNCHER_NAME);
+    return format!(
+        concat!(
+            "[Desktop Entry]\n",
+            "Type=Application\n",
+            "Version=1.0\n",
+            "Name={}\n",
+            "Comment={}\n",
+            "Exec={}\n",
+            "Path={}\n",
+            "Icon={}\n",
+            "Terminal=false\n",
+            "Categories=Game;\n"
+        ),
+        ENTRY_NAME,
+        ENTRY_COMMENT,
+        escape_value(&quote_exec_arg(&launcher.to_string_lossy())),
+        escape_value(&ddlc_dir.to_string_lossy()),
+        escape_value(&icon_path.to_string_lossy())
+    );
+}
+
+/// Writes the file, the journal records it so it's removed on rollback
+fn write_file(path: &Path, data: &[u8], journal: &FileJournal) -> io::Result<()> {
+    if let Some(parent_dir) = path.parent() {
+        journal.before_create_dir(parent_dir);
+        fs::create_dir_all(parent_dir)?;
+    }
+    journal.before_write(path)?;
+    return fs::write(path, data);
+}
+
+/// Creates the desktop entries for the game installed in the given dir,
+/// the icon is written next to the other user icons
+pub fn create_entries(
+    ddlc_dir: &Path,
+    menu_entry: bool,
+    desktop_shortcut: bool,
+    journal: &FileJournal
+) -> io::Result<()> {
+    let paths = get_entry_paths(menu_entry, desktop_shortcut);
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let icon_path = get_icon_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "couldn't find the home dir"))?;
+    write_file(&icon_path, APP_ICON_DATA, journal)?;
+
+    let entry = format_entry(ddlc_dir, &icon_path);
+    for path in paths.iter() {
+        println!("Creating '{}'...", path.display());
+        write_file(path, entry.as_bytes(), journal)?;
+        // The desktop only launches executable entries
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+        }
+    }
+    return Ok(());
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_user_dirs() {
+        let home_dir = Path::new("/home/monika");
+        let content = concat!(
+            "# This file is written by xdg-user-dirs-update\n",
+            "XDG_DOWNLOAD_DIR=\"$HOME/下载\"\n",
+            "XDG_DESKTOP_DIR=\"$HOME/桌面\"\n"
+        );
+        assert_eq!(parse_user_dirs(content, home_dir), Some(home_dir.join("桌面")));
+        assert_eq!(parse_user_dirs("XDG_DESKTOP_DIR=\"/data/desktop\"", home_dir), Some(PathBuf::from("/data/desktop")));
+        assert_eq!(parse_user_dirs("XDG_DESKTOP_DIR=\"desktop\"", home_dir), None);
+        assert_eq!(parse_user_dirs("", home_dir), None);
+    }
+
+    #[test]
+    fn test_format_entry() {
+        let entry = format_entry(Path::new("/games/DDLC 100%"), Path::new("/icons/mas.png"));
+
+        assert!(entry.starts_with("[Desktop Entry]\n"));
+        assert!(entry.contains("Exec=\"/games/DDLC 100%%/DDLC.sh\"\n"));
+        assert!(entry.contains("Path=/games/DDLC 100%\n"));
+        assert!(entry.contains("Icon=/icons/mas.png\n"));
+    }
+
+    #[test]
+    fn test_quote_exec_arg() {
+        assert_eq!(quote_exec_arg("/a/b"), "\"/a/b\"");
+        // The backslashes get escaped twice
+        assert_eq!(escape_value(&quote_exec_arg("/a/$b\\c")), "\"/a/\\\\$b\\\\\\\\c\"");
+    }
+}
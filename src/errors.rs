@@ -1,7 +1,8 @@
 
-use std::io::Error as IOError;
+use std::io::{Error as IOError, ErrorKind as IOErrorKind};
 use std::error::Error as StdError;
 use std::fmt;
+use std::time::SystemTime;
 
 use zip::result::ZipError;
 use reqwest::{
@@ -16,14 +17,14 @@ use rodio::{
     decoder::DecoderError
 };
 
+use crate::preflight::PreflightReport;
+
 
 /// Error type repesenting an error occured during downloading
 #[derive(Debug)]
 pub enum DownloadError {
     /// Got invalid response/failed to send request
     RequestError(ReqError),
-    /// Server failed to provide (valid anyway) content length
-    InvalidContentLen,
     /// Server returned invalid status code
     /// while downloading the assets
     InvalidStatusCode(StatusCode),
@@ -31,6 +32,23 @@ pub enum DownloadError {
     IOError(IOError)
 }
 
+impl DownloadError {
+    /// Checks if the request or a read has timed out, such requests can be retried
+    pub fn is_timeout(&self) -> bool {
+        return match self {
+            Self::RequestError(err) => err.is_timeout(),
+            Self::IOError(err) => {
+                err.kind() == IOErrorKind::TimedOut
+                // reqwest wraps its errors when we read the response
+                || err.get_ref()
+                    .and_then(|e| e.downcast_ref::<ReqError>())
+                    .map_or(false, |e| e.is_timeout())
+            },
+            _ => false
+        };
+    }
+}
+
 impl From<ReqError> for DownloadError {
     fn from(err: ReqError) -> Self {
         return Self::RequestError(err);
@@ -58,9 +76,6 @@ impl fmt::Display for DownloadError {
             Self::RequestError(err) => {
                 write!(f, "failed to request data: {}", err)
             },
-            Self::InvalidContentLen => {
-                write!(f, "GitHub failed to provide content length")
-            }
             Self::InvalidStatusCode(code) => {
                 write!(f, "GitHub returned invalid status code: {}", code)
             },
@@ -79,6 +94,8 @@ pub enum ExtractionError {
     ArchiveError(ZipError),
     /// Unsafe file path in the archive, possible attack?
     UnsafeFilepath(String),
+    /// The archive exceeds the extraction limits, possible zip bomb?
+    LimitExceeded(String),
     /// I/O error
     IOError(IOError)
 }
@@ -113,6 +130,9 @@ impl fmt::Display for ExtractionError {
             Self::UnsafeFilepath(_) => {
                 write!(f, "found unsafe filepath in archive")
             },
+            Self::LimitExceeded(info) => {
+                write!(f, "archive exceeds the limits: {}", info)
+            },
             Self::IOError(err) => {
                 write!(f, "failed to read/write data: {}", err)
             }
@@ -136,7 +156,16 @@ pub enum InstallError {
     /// General IO failure, couldn't write/read
     IOError(IOError),
     /// Error occured during extraction
-    ExtractionError(ExtractionError)
+    ExtractionError(ExtractionError),
+    /// GitHub API rate limit exceeded,
+    /// contains the time when the limit resets if GitHub provided it
+    RateLimited(Option<SystemTime>),
+    /// GitHub API returned an error status code with a message
+    ApiError(StatusCode, String),
+    /// Not enough free space or can't write into the destination
+    PreflightFailed(PreflightReport),
+    /// The release has different components than the ones the user has chosen from
+    ReleaseChanged
 }
 
 impl From<SerdeError> for InstallError {
@@ -198,6 +227,28 @@ impl fmt::Display for InstallError {
             },
             Self::ExtractionError(err) => {
                 write!(f, "extraction failed: {}", err)
+            },
+            Self::RateLimited(reset_at) => {
+                let wait_mins = reset_at
+                    .and_then(|t| t.duration_since(SystemTime::now()).ok())
+                    .map(|d| d.as_secs() / 60 + 1);
+                match wait_mins {
+                    Some(mins) => write!(
+                        f,
+                        "GitHub API rate limit exceeded, try again in {} min (or set a GitHub token)",
+                        mins
+                    ),
+                    None => write!(f, "GitHub API rate limit exceeded, try again later (or set a GitHub token)")
+                }
+            },
+            Self::ApiError(code, msg) => {
+                write!(f, "GitHub API returned {}: {}", code, msg)
+            },
+            Self::PreflightFailed(report) => {
+                write!(f, "pre-install checks failed:\n{}", report)
+            },
+            Self::ReleaseChanged => {
+                write!(f, "the release has changed, please review your choices")
             }
         };
     }
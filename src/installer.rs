@@ -1,14 +1,17 @@
 /// The module that implements installer logic for IO handling
 
 use std::{
-    env,
-    path::Path,
-    fs::{File, create_dir_all, read_dir, remove_file, remove_dir_all},
-    io,
+    path::{Path, PathBuf},
+    fs::{File, create_dir_all, read_dir},
+    io::{self, Read, Seek, SeekFrom, Write},
     cmp::min,
-    collections::HashMap,
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering}
+    },
     thread,
-    time::Duration
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH}
 };
 
 use fltk::app::{
@@ -17,41 +20,108 @@ use fltk::app::{
 
 use ::reqwest::{
     blocking as reqwest,
-    header as headers
+    header as headers,
+    StatusCode
 };
 
+use regex::Regex;
+
 use serde::{Serialize, Deserialize};
 
-use zip::ZipArchive;
+use zip::{ZipArchive, DateTime, read::{ZipFile, read_zipfile_from_stream}, result::ZipError};
+
+use chrono::{Duration as ChronoDuration, Local, LocalResult, NaiveDate, TimeZone};
+
+use filetime::FileTime;
 
 use crate::{
     app::{
         state::ThreadSafeState,
         Message
     },
+    bandwidth::{RateLimiter, ThrottledReader},
+    cache,
+    cancellation::{
+        AbortableReader,
+        CancellableReader,
+        CancellationToken,
+        PauseToken,
+        run_cancellable
+    },
+    chunking::ChunkSizer,
+    components::{
+        Component,
+        ComponentManifest,
+        MANIFEST_ASSET_NAME,
+        get_default_components,
+        get_game_dir
+    },
+    config::{ExtractionLimits, InstallerConfig, NetworkTimeouts},
+    desktop_entry,
+    preflight::PreflightReport,
+    rollback::FileJournal,
+    session::{InstallSession, InstallStep, TempFile},
+    streaming::{
+        self,
+        EntryInfo,
+        CENTRAL_DIR_HEADER_SIGNATURE,
+        MAX_CENTRAL_DIR_SIZE
+    },
     errors::{
         InstallError,
         DownloadError,
         ExtractionError
-    }
+    },
+    submods::{
+        InstalledSubmod,
+        SubmodsRecord,
+        SUBMODS_DIR_PATH,
+        remove_submod_files
+    },
+    utils
 };
 
 
 const PAUSE_DURATION: Duration = Duration::from_millis(200);
+// How often we check for the user input from the installer thread
+const POLL_DURATION: Duration = Duration::from_millis(100);
+// Max number of threads we extract an archive with
+const MAX_EXTRACTION_THREADS: usize = 8;
+// We don't spawn a thread for fewer entries than this
+const MIN_ENTRIES_PER_THREAD: usize = 64;
+// Images with these words in their names are preferred as previews
+const PREVIEW_NAMES: [&str; 3] = ["preview", "thumb", "icon"];
+// We don't load previews larger than this
+const MAX_PREVIEW_SIZE: u64 = 4*1024*1024;
+// Name of the cache entry with the latest release
+const RELEASE_CACHE_NAME: &str = "latest_release";
+// Format version of the release cache, bump it when the cached data changes
+const RELEASE_CACHE_VERSION: u32 = 1;
+// We only send the GitHub token to this host
+const GITHUB_API_HOST: &str = "api.github.com";
+// How many times in a row we retry a range request that has timed out
+const MAX_RANGE_RETRIES: u32 = 3;
+// Buffer size for the downloads we copy by hand
+const COPY_BUF_SIZE: usize = 64*1024;
+// How often we report the progress of a download with unknown size
+const PULSE_PROGRESS_STEP: ContentSize = 256*1024;
 
 
 pub type InstallResult = Result<(), InstallError>;
 pub type ContentSize = u64;
+// Components with their indices in the release
+type IndexedComponents<'a> = Vec<(usize, &'a Component)>;
 
 /// Struct representing release data we may need
-#[derive(Debug)]
-#[allow(dead_code)]
-struct ReleaseData {
+#[derive(Debug, Clone)]
+pub struct ReleaseData {
     version: String,
     name: String,
-    def_ver_asset: GHAsset,
-    dlx_ver_asset: GHAsset,
-    spr_asset: GHAsset
+    // Markdown, can be empty
+    changelog: String,
+    components: Vec<Component>,
+    // Maps component ids to their assets
+    assets: HashMap<String, GHAsset>
 }
 
 impl ReleaseData {
@@ -59,17 +129,42 @@ impl ReleaseData {
     pub fn new(
         version: String,
         name: String,
-        def_ver_asset: GHAsset,
-        dlx_ver_asset: GHAsset,
-        spr_asset: GHAsset
+        changelog: String,
+        components: Vec<Component>,
+        assets: HashMap<String, GHAsset>
     ) -> Self {
-        return Self { version, name, def_ver_asset, dlx_ver_asset, spr_asset };
+        return Self { version, name, changelog, components, assets };
+    }
+
+    /// Returns the version (tag) of the release
+    pub fn get_version(&self) -> &str {
+        return &self.version;
+    }
+
+    /// Returns the name of the release
+    pub fn get_name(&self) -> &str {
+        return &self.name;
+    }
+
+    /// Returns the changelog of the release in markdown
+    pub fn get_changelog(&self) -> &str {
+        return &self.changelog;
+    }
+
+    /// Returns the components available in this release
+    pub fn get_components(&self) -> &[Component] {
+        return &self.components;
+    }
+
+    /// Returns the asset of the component with the given id
+    pub fn get_asset(&self, component_id: &str) -> Option<&GHAsset> {
+        return self.assets.get(component_id);
     }
 }
 
 /// Represents an attachment in a GitHub release
-#[derive(Serialize, Deserialize, Debug)]
-struct GHAsset {
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GHAsset {
     name: String,
     size: ContentSize,
     browser_download_url: String
@@ -86,6 +181,17 @@ impl GHAsset {
             && self.browser_download_url.ends_with(".zip")
         };
     }
+
+    /// Returns the size of the asset
+    pub fn get_size(&self) -> ContentSize {
+        return self.size;
+    }
+}
+
+/// Represents an error response of GitHub API
+#[derive(Serialize, Deserialize, Debug)]
+struct GHErrorResponse {
+    message: String
 }
 
 /// Represents a GitHub release
@@ -93,6 +199,9 @@ impl GHAsset {
 struct GHRelease {
     tag_name: String,
     name: String,
+    // The description of the release, GitHub sends null if it's empty
+    #[serde(default)]
+    body: Option<String>,
     assets: Vec<GHAsset>
 }
 
@@ -107,6 +216,20 @@ impl GHRelease {
     }
 }
 
+/// Represents the cached response of the release API,
+/// the validators are used for conditional requests
+#[derive(Serialize, Deserialize, Debug)]
+struct ReleaseCache {
+    // The caches from the older versions have no version
+    #[serde(default)]
+    version: u32,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    release: GHRelease,
+    #[serde(default)]
+    manifest: Option<ComponentManifest>
+}
+
 
 /// Blocks the thread for PAUSE_DURATION seconds
 fn sleep() {
@@ -115,81 +238,333 @@ fn sleep() {
 
 
 /// Builds a client for this installer to access GitHub API
-pub fn build_client() -> Result<reqwest::Client, InstallError> {
+/// NOTE: the client doesn't authenticate, use build_api_request for the API requests
+pub fn build_client(config: &InstallerConfig) -> Result<reqwest::Client, InstallError> {
     let headers = crate::HEADERS.clone();
-    let client = reqwest::Client::builder()
+    // The read timeout applies to the response and to every read of its body,
+    // None disables the default one
+    let mut builder = reqwest::Client::builder()
         .default_headers(headers)
-        .build()?;
+        .connect_timeout(config.timeouts.get_connect())
+        .timeout(config.timeouts.get_read());
+
+    // If there's no explicit proxy, reqwest will use the env vars
+    if let Some(ref proxy_config) = config.proxy {
+        let mut proxy = ::reqwest::Proxy::all(proxy_config.get_url())?;
+        if let Some((username, password)) = proxy_config.get_auth() {
+            proxy = proxy.basic_auth(username, password);
+        }
+        builder = builder.proxy(proxy);
+    }
+
+    let client = builder.build()?;
 
     return Ok(client);
 }
 
+/// Checks if the url points to the GitHub API
+fn is_github_api_url(url: &str) -> bool {
+    return ::reqwest::Url::parse(url)
+        .map(|url| url.scheme() == "https" && url.host_str() == Some(GITHUB_API_HOST))
+        .unwrap_or(false);
+}
 
-/// Requests release data from github
-fn get_release_data(client: &reqwest::Client) -> Result<ReleaseData, InstallError> {
-    let data = client.get(
-        format!(
-            "https://api.github.com/repos/{}/{}/releases/latest",
-            crate::ORG_NAME,
-            crate::REPO_NAME
-        )
-    ).send()?.bytes()?;
+/// Builds a GET request, the GitHub token is only added for the GitHub API,
+/// so it doesn't leak to the other hosts (e.g. a custom submods catalog)
+fn build_api_request(client: &reqwest::Client, url: &str, github_token: Option<&str>) -> reqwest::RequestBuilder {
+    let req = client.get(url);
+    let github_token = match github_token {
+        Some(token) if is_github_api_url(url) => token,
+        _ => return req
+    };
+    return match headers::HeaderValue::from_str(&format!("Bearer {github_token}")) {
+        Ok(mut value) => {
+            value.set_sensitive(true);
+            req.header(headers::AUTHORIZATION, value)
+        },
+        Err(_) => {
+            eprintln!("GitHub token is invalid, ignoring it");
+            req
+        }
+    };
+}
+
+/// Checks if we can access the release API using the given config
+/// NOTE: this ignores the cache
+pub fn test_connection(config: &InstallerConfig) -> Result<(), InstallError> {
+    let client = build_client(config)?;
+    if let Some(cached) = fetch_release(&client, config.get_github_token(), None)? {
+        parse_release(&cached.release, cached.manifest.as_ref())?;
+    }
+
+    return Ok(());
+}
+
+
+/// Reads a numeric header from the response
+fn get_header_num(resp: &reqwest::Response, name: &str) -> Option<u64> {
+    return resp.headers().get(name)?.to_str().ok()?.trim().parse::<u64>().ok();
+}
+
+/// Checks GitHub API response for errors, rate limit in particular
+fn check_api_response(resp: reqwest::Response) -> Result<reqwest::Response, InstallError> {
+    let remaining = get_header_num(&resp, "x-ratelimit-remaining");
+    let reset_at = get_header_num(&resp, "x-ratelimit-reset")
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+
+    if let Some(remaining) = remaining {
+        println!("GitHub API requests remaining: {}", remaining);
+    }
+
+    let status_code = resp.status();
+    if status_code.is_success() {
+        return Ok(resp);
+    }
+
+    let retry_after = get_header_num(&resp, "retry-after")
+        .map(|secs| SystemTime::now() + Duration::from_secs(secs));
+    let message = match resp.bytes() {
+        Ok(body) => serde_json::from_slice::<GHErrorResponse>(&body)
+            .map(|r| r.message)
+            .unwrap_or_else(|_| String::from_utf8_lossy(&body).into_owned()),
+        Err(_) => String::new()
+    };
+
+    let is_rate_limited = match status_code {
+        StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS => {
+            remaining == Some(0)
+            || retry_after.is_some()
+            || message.to_lowercase().contains("rate limit")
+        },
+        _ => false
+    };
+    if is_rate_limited {
+        return Err(InstallError::RateLimited(retry_after.or(reset_at)));
+    }
+
+    return Err(InstallError::ApiError(status_code, message));
+}
+
+/// Reads a string header from the response
+fn get_header_str(resp: &reqwest::Response, name: headers::HeaderName) -> Option<String> {
+    return resp.headers().get(name)?.to_str().ok().map(str::to_string);
+}
+
+/// Requests the latest release from github
+/// If the cached release is provided, sends a conditional request
+/// and returns None if the release hasn't changed
+fn fetch_release(
+    client: &reqwest::Client,
+    github_token: Option<&str>,
+    cached: Option<&ReleaseCache>
+) -> Result<Option<ReleaseCache>, InstallError> {
+    let url = format!(
+        "https://{}/repos/{}/{}/releases/latest",
+        GITHUB_API_HOST,
+        crate::ORG_NAME,
+        crate::REPO_NAME
+    );
+    let mut req = build_api_request(client, &url, github_token);
+    if let Some(cached) = cached {
+        if let Some(ref etag) = cached.etag {
+            req = req.header(headers::IF_NONE_MATCH, etag);
+        }
+        if let Some(ref last_modified) = cached.last_modified {
+            req = req.header(headers::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let resp = req.send()?;
+    if cached.is_some() && resp.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    let resp = check_api_response(resp)?;
+
+    let etag = get_header_str(&resp, headers::ETAG);
+    let last_modified = get_header_str(&resp, headers::LAST_MODIFIED);
+    let data = resp.bytes()?;
+    let release: GHRelease = serde_json::from_slice(&data)?;
+    let manifest = fetch_manifest(client, &release);
+
+    return Ok(Some(ReleaseCache {
+        version: RELEASE_CACHE_VERSION,
+        etag,
+        last_modified,
+        release,
+        manifest
+    }));
+}
 
+/// Requests the latest release of the given GitHub repo (owner/name)
+fn fetch_repo_release(client: &reqwest::Client, github_token: Option<&str>, repo: &str) -> Result<GHRelease, InstallError> {
+    let url = format!("https://{}/repos/{}/releases/latest", GITHUB_API_HOST, repo);
+    let resp = build_api_request(client, &url, github_token).send()?;
+    let data = check_api_response(resp)?.bytes()?;
     let release: GHRelease = serde_json::from_slice(&data)?;
     if !release.is_valid() {
         eprintln!("Release '{:?}' is invalid", release);
         return Err(InstallError::CorruptedJSON("Latest release is invalid"));
     }
 
-    // Create a map of the assets we need
-    let mut assets_map = HashMap::new();
+    return Ok(release);
+}
 
-    // Search thru all the available assets and find the ones we need
-    'outer_loop: for asset in release.assets {
-        // Quit early if we filled the map
-        if assets_map.len() == crate::ASSETS_NAMES_RE_MAP.len() {
-            break;
-        }
-        // Use regex to find the assets
-        for (k, v) in crate::ASSETS_NAMES_RE_MAP.iter() {
-            if !assets_map.contains_key(k) && v.is_match(&asset.name) {
-                if !asset.is_valid() {
-                    eprintln!("Asset '{}' is invalid", asset.name);
-                    return Err(InstallError::CorruptedJSON("Found a required asset, but it's invalid"));
-                }
-                assets_map.insert(*k, asset);
-                // We need to move to the next asset since this once has been moved
-                continue 'outer_loop;
-            }
+/// Downloads the components manifest if it's attached to the release
+/// This function is "best-effort", on failure we use the default components
+fn fetch_manifest(client: &reqwest::Client, release: &GHRelease) -> Option<ComponentManifest> {
+    let asset = release.assets.iter().find(|a| a.name == MANIFEST_ASSET_NAME)?;
+
+    let resp = match client.get(&asset.browser_download_url).send() {
+        Ok(resp) => resp,
+        Err(e) => {
+            eprintln!("Failed to download components manifest: {e}");
+            return None;
+        }
+    };
+    if !resp.status().is_success() {
+        eprintln!("Failed to download components manifest: {}", resp.status());
+        return None;
+    }
+
+    let manifest: ComponentManifest = match resp.bytes().map(|data| serde_json::from_slice(&data)) {
+        Ok(Ok(manifest)) => manifest,
+        _ => {
+            eprintln!("Components manifest is corrupted");
+            return None;
         }
+    };
+    if !manifest.is_valid() {
+        eprintln!("Components manifest '{:?}' is invalid", manifest);
+        return None;
+    }
+
+    return Some(manifest);
+}
+
+/// Parses the release, finds the assets for the components
+/// If there's no manifest, uses the default components
+fn parse_release(release: &GHRelease, manifest: Option<&ComponentManifest>) -> Result<ReleaseData, InstallError> {
+    if !release.is_valid() {
+        eprintln!("Release '{:?}' is invalid", release);
+        return Err(InstallError::CorruptedJSON("Latest release is invalid"));
     }
 
-    if assets_map.len() != crate::ASSETS_NAMES_RE_MAP.len() {
-        return Err(InstallError::CorruptedJSON("An asset is missing from the release"));
+    let components = match manifest {
+        Some(manifest) => manifest.components.clone(),
+        None => get_default_components()
+    };
+
+    // Create a map of the assets we need
+    let mut assets_map = HashMap::new();
+
+    // Use regex to find the assets
+    for component in components.iter() {
+        let asset_re = component.get_asset_re()
+            .map_err(|_| InstallError::CorruptedJSON("Found a component with invalid asset pattern"))?;
+
+        let asset = release.assets.iter()
+            .find(|a| asset_re.is_match(&a.name))
+            .ok_or(InstallError::CorruptedJSON("An asset is missing from the release"))?;
+        if !asset.is_valid() {
+            eprintln!("Asset '{}' is invalid", asset.name);
+            return Err(InstallError::CorruptedJSON("Found a required asset, but it's invalid"));
+        }
+        assets_map.insert(component.id.clone(), asset.clone());
     }
 
     let data = ReleaseData::new(
-        release.tag_name,
-        release.name,
-        assets_map.remove("def_ver").unwrap(),
-        assets_map.remove("dlx_ver").unwrap(),
-        assets_map.remove("spr").unwrap()
+        release.tag_name.clone(),
+        release.name.clone(),
+        release.body.clone().unwrap_or_default(),
+        components,
+        assets_map
     );
     return Ok(data);
 }
 
+/// Requests release data from github
+/// Uses the cache to avoid downloading the same data and falls back to it
+/// if we can't access GitHub
+fn get_release_data(client: &reqwest::Client, github_token: Option<&str>) -> Result<ReleaseData, InstallError> {
+    let cached: Option<ReleaseCache> = cache::load(RELEASE_CACHE_NAME);
+    // The caches from the older versions may lack some data (e.g. the changelog), don't reuse them
+    let validated_cache = cached.as_ref().filter(|c| c.version == RELEASE_CACHE_VERSION);
+
+    let release_cache = match fetch_release(client, github_token, validated_cache) {
+        Ok(Some(fresh)) => {
+            // Only cache valid releases
+            let data = parse_release(&fresh.release, fresh.manifest.as_ref())?;
+            cache::save(RELEASE_CACHE_NAME, &fresh);
+            return Ok(data);
+        },
+        Ok(None) => {
+            println!("Release data hasn't changed, using cache...");
+            cached
+        },
+        // GitHub can't give us the data right now, stale data is better than nothing
+        Err(e) if matches!(e, InstallError::RequestError(_) | InstallError::RateLimited(_))
+            || matches!(e, InstallError::ApiError(code, _) if code.is_server_error()) => {
+            if cached.is_none() {
+                return Err(e);
+            }
+            eprintln!("Failed to fetch release data, using cache: {e}");
+            cached
+        },
+        Err(e) => return Err(e)
+    };
+
+    // Safe to unwrap, we only get here if we had the cache
+    let release_cache = release_cache.unwrap();
+    return parse_release(&release_cache.release, release_cache.manifest.as_ref());
+}
+
+/// Returns the cached release data, doesn't access the network
+pub fn load_cached_release_data() -> Option<ReleaseData> {
+    let cached: ReleaseCache = cache::load(RELEASE_CACHE_NAME)?;
+    return parse_release(&cached.release, cached.manifest.as_ref()).ok();
+}
+
+/// Requests release data in a separate thread, on success the data is saved into the app state
+/// and the ReleaseDataLoaded message is sent, otherwise the ReleaseDataFailed message is sent
+pub fn fetch_release_data_in_thread(
+    sender: Sender<Message>,
+    app_state: &ThreadSafeState
+) -> thread::JoinHandle<()> {
+    let app_state = app_state.clone();
+
+    return thread::spawn(
+        move || {
+            let config = app_state.lock().unwrap().get_config().clone();
+            let rv = build_client(&config)
+                .and_then(|client| get_release_data(&client, config.get_github_token()));
+            match rv {
+                Ok(data) => {
+                    app_state.lock().unwrap().set_release_data(Some(data));
+                    sender.send(Message::ReleaseDataLoaded);
+                },
+                Err(e) => {
+                    eprintln!("Failed to fetch release data: {e}");
+                    sender.send(Message::ReleaseDataFailed);
+                }
+            };
+        }
+    );
+}
 
-/// Prepares a DDLC directory (unlinks some files/folders at the given path)
+
+/// Returns the files/folders prepare_ddlc_dir would unlink at the given path
 /// This function is "best-effort" and will ignore errors
 /// TPG, I know you will love these nested ifs
-fn prepare_ddlc_dir(path: &Path, remove_rpy: bool, remove_bin: bool) {
+pub fn get_ddlc_dir_removals(path: &Path, remove_rpy: bool, remove_bin: bool) -> Vec<PathBuf> {
+    let mut rv = Vec::new();
+
     if !path.is_dir() {
-        return;
+        return rv;
     }
 
     let content = read_dir(path);
     if content.is_err() {
-        return;
+        return rv;
     }
 
     let content = content.unwrap();
@@ -207,9 +582,7 @@ fn prepare_ddlc_dir(path: &Path, remove_rpy: bool, remove_bin: bool) {
                     let ext = ext.unwrap();
                     match ext {
                         "rpy" | "rpyc" => {
-                            if remove_file(&item_path).is_err() {
-                                eprintln!("Failed to delete '{}'", item_path.display());
-                            }
+                            rv.push(item_path);
                         },
                         _ => {}
                     }
@@ -226,9 +599,7 @@ fn prepare_ddlc_dir(path: &Path, remove_rpy: bool, remove_bin: bool) {
                     let dir_name = dir_name.unwrap();
                     match dir_name {
                         "renpy" | "lib" => {
-                            if remove_dir_all(&item_path).is_err() {
-                                eprintln!("Failed to delete '{}'", item_path.display());
-                            }
+                            rv.push(item_path);
                         }
                         _ => {}
                     }
@@ -236,19 +607,232 @@ fn prepare_ddlc_dir(path: &Path, remove_rpy: bool, remove_bin: bool) {
             }
         }
     }
+
+    return rv;
+}
+
+/// Returns everything we remove from the game dir before extracting
+pub fn get_install_removals(game_dir: &Path) -> Vec<PathBuf> {
+    // Remove old rpy/rpyc
+    // Yeah...some people have rpy in the base dir...
+    let mut rv = get_ddlc_dir_removals(game_dir, true, false);
+    rv.extend(get_ddlc_dir_removals(&game_dir.join("game"), true, false));
+
+    return rv;
+}
+
+/// Prepares a DDLC directory (unlinks some files/folders in the game dir)
+/// The files are removed via the journal, so we can restore them
+/// This function is "best-effort" and will ignore errors
+fn prepare_ddlc_dir(game_dir: &Path, journal: &FileJournal) {
+    for path in get_install_removals(game_dir) {
+        if journal.remove(&path).is_err() {
+            eprintln!("Failed to delete '{}'", path.display());
+        }
+    }
 }
 
 
+/// Sends the request in the background, so we can stop waiting for the response
+/// once the token is cancelled
+fn send_request(token: &CancellationToken, request: reqwest::RequestBuilder) -> Result<reqwest::Response, DownloadError> {
+    let resp = run_cancellable(token, move || request.send())??;
+    return Ok(resp);
+}
+
 /// Tries to query content len on the given link
-fn get_content_size(client: &reqwest::Client, download_link: &str) -> Result<ContentSize, DownloadError> {
-    let resp = client.head(download_link).send()?;
+/// Returns None if the server doesn't tell it, some mirrors and proxies don't
+fn get_content_size(client: &reqwest::Client, token: &CancellationToken, download_link: &str) -> Result<Option<ContentSize>, DownloadError> {
+    let resp = send_request(token, client.head(download_link))?;
+    if !resp.status().is_success() {
+        eprintln!("HEAD request returned {}, the size is unknown", resp.status());
+        return Ok(None);
+    }
     let content_size = resp.headers().get(headers::CONTENT_LENGTH)
-        .ok_or(DownloadError::InvalidContentLen)?
-        .to_str().ok().ok_or(DownloadError::InvalidContentLen)?
-        .parse::<ContentSize>().ok().ok_or(DownloadError::InvalidContentLen)?;
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<ContentSize>().ok());
     return Ok(content_size);
 }
 
+/// Parses the first byte of the range from the Content-Range header,
+/// e.g. "bytes 100-199/1000" or "bytes 100-199/*"
+fn parse_content_range_start(value: &str) -> Option<ContentSize> {
+    let range = value.trim().strip_prefix("bytes ")?;
+    let (start, _) = range.trim_start().split_once('-')?;
+    return start.parse::<ContentSize>().ok();
+}
+
+/// Checks if the response is the part of the file that starts at the given offset
+fn is_partial_content_at(resp: &reqwest::Response, offset: ContentSize) -> bool {
+    if resp.status() != StatusCode::PARTIAL_CONTENT {
+        return false;
+    }
+    return resp.headers().get(headers::CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_content_range_start)
+        == Some(offset);
+}
+
+/// The client, the tokens and the settings the download functions share
+struct DownloadContext<'a> {
+    client: &'a reqwest::Client,
+    sender: Sender<Message>,
+    token: CancellationToken,
+    pause_token: PauseToken,
+    limiter: RateLimiter,
+    timeouts: NetworkTimeouts
+}
+
+impl<'a> DownloadContext<'a> {
+    /// Creates a new context using the tokens and the config from the app state
+    fn new(client: &'a reqwest::Client, sender: Sender<Message>, app_state: &ThreadSafeState) -> Self {
+        let app_state = app_state.lock().unwrap();
+        let config = app_state.get_config();
+        return Self {
+            client,
+            sender,
+            token: app_state.get_cancel_token(),
+            pause_token: app_state.get_pause_token(),
+            limiter: RateLimiter::new(config.get_download_speed_limit()),
+            timeouts: config.timeouts.clone()
+        };
+    }
+}
+
+/// Requests the given range (the bounds are inclusive) and writes it into the file
+/// Returns the number of received bytes, the time until the response and the time we've read the body for,
+/// None if the server has ignored the range and sent the whole file
+fn download_range(
+    ctx: &DownloadContext,
+    download_link: &str,
+    (low_bound, up_bound): (ContentSize, ContentSize),
+    file: &mut File
+) -> Result<Option<(ContentSize, Duration, Duration)>, DownloadError> {
+    let request_start = Instant::now();
+    let resp = send_request(
+        &ctx.token,
+        ctx.client
+            .get(download_link)
+            .header(headers::RANGE, format!("bytes={}-{}", low_bound, up_bound))
+    )?;
+    let latency = request_start.elapsed();
+
+    let status_code = resp.status();
+    if status_code == StatusCode::OK {
+        return Ok(None);
+    }
+    // Writing a different range would corrupt the file
+    if !is_partial_content_at(&resp, low_bound) {
+        return Err(DownloadError::InvalidStatusCode(status_code));
+    }
+
+    // Write the received data, the reader lets us cancel or retry a stalled read
+    let transfer_start = Instant::now();
+    let reader = AbortableReader::new(resp, &ctx.token)
+        .with_stall_timeout(ctx.timeouts.get_stall())
+        .with_deadline(ctx.timeouts.get_request().map(|timeout| request_start + timeout));
+    let mut reader = ThrottledReader::new(reader, &ctx.limiter, &ctx.token);
+    let received = io::copy(&mut reader, file)? as ContentSize;
+
+    return Ok(Some((received, latency, transfer_start.elapsed())));
+}
+
+/// Makes a single GET request and writes the rest of the file,
+/// continues from the end of the file if the server supports ranges, otherwise starts over
+fn download_stream_once(
+    ctx: &DownloadContext,
+    download_link: &str,
+    content_size: Option<ContentSize>,
+    accepts_ranges: bool,
+    file: &mut File
+) -> Result<(), DownloadError> {
+    let mut offset: ContentSize = file.seek(SeekFrom::End(0))?;
+    if content_size.map_or(false, |size| offset >= size) {
+        ctx.sender.send(Message::UpdateProgressBar(1.0));
+        return Ok(());
+    }
+
+    let mut request = ctx.client.get(download_link);
+    if offset > 0 && accepts_ranges {
+        println!("Resuming download from {} bytes...", offset);
+        request = request.header(headers::RANGE, format!("bytes={}-", offset));
+    }
+    let resp = send_request(&ctx.token, request)?;
+
+    let status_code = resp.status();
+    // We already have the whole file, but didn't know its size
+    if offset > 0 && status_code == StatusCode::RANGE_NOT_SATISFIABLE && content_size.is_none() {
+        return Ok(());
+    }
+    if offset > 0 && !is_partial_content_at(&resp, offset) {
+        if status_code != StatusCode::OK {
+            return Err(DownloadError::InvalidStatusCode(status_code));
+        }
+        println!("Starting the download over...");
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        offset = 0;
+    }
+    else if !status_code.is_success() {
+        return Err(DownloadError::InvalidStatusCode(status_code));
+    }
+    let content_size = content_size.or_else(|| resp.content_length().map(|len| offset + len));
+
+    let reader = AbortableReader::new(resp, &ctx.token).with_stall_timeout(ctx.timeouts.get_stall());
+    let reader = ThrottledReader::new(reader, &ctx.limiter, &ctx.token);
+    let mut reader = ProgressReader::new(reader, ctx.sender, content_size).with_offset(offset);
+
+    // We can't make another request without ranges, so we keep the connection while paused
+    let mut buf = vec![0; COPY_BUF_SIZE];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into())
+        };
+        file.write_all(&buf[..n])?;
+
+        if ctx.pause_token.is_paused() {
+            println!("Download paused...");
+            ctx.pause_token.wait(&ctx.token);
+            println!("Resuming download...");
+        }
+        if ctx.token.is_cancelled() {
+            return Ok(());
+        }
+    }
+
+    return Ok(());
+}
+
+/// Downloads the file with a single streaming GET, for the servers that don't support ranges
+/// or don't tell us the size. The request is retried if it times out
+fn download_stream(
+    ctx: &DownloadContext,
+    download_link: &str,
+    content_size: Option<ContentSize>,
+    accepts_ranges: bool,
+    file: &mut File
+) -> Result<(), DownloadError> {
+    let mut retries: u32 = 0;
+    loop {
+        let start_size = file.seek(SeekFrom::End(0))?;
+        let rv = download_stream_once(ctx, download_link, content_size, accepts_ranges, file);
+        match rv {
+            Err(e) if e.is_timeout() && !ctx.token.is_cancelled() && retries < MAX_RANGE_RETRIES => {
+                // Only count the attempts without any progress
+                retries = match file.seek(SeekFrom::End(0))? > start_size {
+                    true => 0,
+                    false => retries + 1
+                };
+                eprintln!("Download has timed out ({e}), retrying ({retries}/{MAX_RANGE_RETRIES})...");
+            },
+            rv => return rv
+        };
+    }
+}
+
 /// Downloads data from the given link using the provided client
 /// the data is being written into the given file handler
 fn download_to_file(
@@ -259,39 +843,72 @@ fn download_to_file(
     content_size: Option<ContentSize>,
     file: &mut File
 ) -> Result<(), DownloadError> {
-    const DEF_CHUNK_SIZE: ContentSize = 1024*1024*8 + 1;
-
     sender.send(Message::UpdateProgressBar(0.0));
 
-    if app_state.lock().unwrap().get_abort_flag() {
+    let ctx = DownloadContext::new(client, sender, app_state);
+    if ctx.token.is_cancelled() {
         return Ok(());
     }
 
+    let content_size: Option<ContentSize> = match content_size {
+        None => get_content_size(client, &ctx.token, download_link)?,
+        v => v
+    };
     let content_size: ContentSize = match content_size {
-        None => get_content_size(client, download_link)?,
-        Some(v) => v
+        Some(v) => v,
+        None => {
+            println!("The server didn't provide the size, downloading in a single request...");
+            return download_stream(&ctx, download_link, None, true, file);
+        }
     };
 
-    let chunk_size: ContentSize = min(DEF_CHUNK_SIZE, content_size);
-    let mut low_bound: ContentSize = 0;
-    let mut up_bound: ContentSize = chunk_size;
-    let mut total_downloaded: ContentSize = 0;
+    // Continue from where we've stopped if the file already has some data
+    let mut total_downloaded: ContentSize = file.seek(SeekFrom::End(0))?;
+    if total_downloaded >= content_size {
+        sender.send(Message::UpdateProgressBar(1.0));
+        return Ok(());
+    }
+    if total_downloaded > 0 {
+        println!("Resuming download from {} bytes...", total_downloaded);
+    }
+
+    // The chunk size adapts to the link using the measurements of the previous chunks
+    let mut sizer = ChunkSizer::new();
+    let mut low_bound: ContentSize = total_downloaded;
+    let mut retries: u32 = 0;
 
     // println!("Content size: {}", content_size);
     loop {
-        // println!("{}-{}", low_bound, up_bound-1);
-        let mut resp = client
-            .get(download_link)
-            .header(headers::RANGE, format!("bytes={}-{}", low_bound, up_bound-1))
-            .send()?;
-
-        let status_code = resp.status();
-        if !status_code.is_success() {
-            return Err(DownloadError::InvalidStatusCode(status_code));
-        }
-
-        // Write the received data
-        let received_chunk = resp.copy_to(file)? as ContentSize;
+        // The bounds are inclusive
+        let up_bound: ContentSize = min(low_bound + sizer.get_chunk_size(), content_size) - 1;
+        let requested_chunk = up_bound - low_bound + 1;
+        // println!("{}-{}", low_bound, up_bound);
+        let received_chunk = match download_range(&ctx, download_link, (low_bound, up_bound), file) {
+            Ok(Some((received_chunk, latency, transfer_time))) => {
+                retries = 0;
+                sizer.update(requested_chunk, received_chunk, latency, transfer_time);
+                received_chunk
+            },
+            Ok(None) => {
+                println!("The server doesn't support ranges, downloading in a single request...");
+                return download_stream(&ctx, download_link, Some(content_size), false, file);
+            },
+            Err(e) if e.is_timeout() && !ctx.token.is_cancelled() && retries < MAX_RANGE_RETRIES => {
+                // Keep the data we've got before the timeout
+                let file_size = file.seek(SeekFrom::End(0))?;
+                // Only count the attempts without any progress, a slow link can still finish the chunk
+                retries = match file_size > low_bound {
+                    true => 0,
+                    false => retries + 1
+                };
+                eprintln!("Range {}-{} has timed out ({e}), retrying ({retries}/{MAX_RANGE_RETRIES})...", low_bound, up_bound);
+                sizer.shrink("timed out");
+                total_downloaded = file_size;
+                low_bound = total_downloaded;
+                continue;
+            },
+            Err(e) => return Err(e)
+        };
         total_downloaded += received_chunk;
 
         // Update progress bar
@@ -305,16 +922,17 @@ fn download_to_file(
             break
         }
 
-        // In case the server returned less than we asked, we need to
-        // ask for the missing bits, so adjust the chunk size here
-        let bound_inc = min(received_chunk, chunk_size);
-        // Increment the bounds
-        low_bound += bound_inc;
-        up_bound = min(up_bound+bound_inc, content_size+1);
-        // Slep to let the server rest
-        sleep();
+        // In case the server returned less than we asked,
+        // the next request asks for the missing bits
+        low_bound += min(received_chunk, requested_chunk);
+        // Wait while the user has paused the download, the next request continues from low_bound
+        if ctx.pause_token.is_paused() {
+            println!("Download paused at {} bytes...", low_bound);
+            ctx.pause_token.wait(&ctx.token);
+            println!("Resuming download from {} bytes...", low_bound);
+        }
         // See if we want to abort
-        if app_state.lock().unwrap().get_abort_flag() {
+        if ctx.token.is_cancelled() {
             return Ok(());
         }
     }
@@ -325,85 +943,859 @@ fn download_to_file(
 }
 
 
-/// Extracts a zip archive
-fn extract_archive(
-    sender: Sender<Message>,
-    app_state: &ThreadSafeState,
-    archive: &File,
-    destination: &Path
-) -> Result<(), ExtractionError> {
-    sender.send(Message::UpdateProgressBar(0.0));
+/// Returns the top-level dir of the given path within an archive,
+/// None if the path is a file at the root of the archive
+fn get_top_level_dir(path: &Path, is_dir: bool) -> Option<String> {
+    let mut components = path.components();
+    let first = components.next()?;
+    if !is_dir && components.next().is_none() {
+        return None;
+    }
+    return Some(first.as_os_str().to_string_lossy().into_owned());
+}
 
-    if app_state.lock().unwrap().get_abort_flag() {
-        return Ok(());
+/// Lists the top-level dirs of a zip archive, sorted by name
+fn list_top_level_dirs(archive: &File) -> Result<Vec<String>, ExtractionError> {
+    let archive = ZipArchive::new(archive)?;
+    let dirs: BTreeSet<String> = archive.file_names()
+        .filter_map(|name| get_top_level_dir(Path::new(name), name.ends_with('/')))
+        .filter(|dir| dir != ".." && dir != ".")
+        .collect();
+
+    return Ok(dirs.into_iter().collect());
+}
+
+/// Returns how good the path is as a preview image, lower is better
+/// None if the path isn't a PNG image
+fn get_preview_rank(path: &Path) -> Option<(bool, usize)> {
+    let is_png = path.extension().map_or(false, |ext| ext.eq_ignore_ascii_case("png"));
+    if !is_png {
+        return None;
     }
+    let stem = path.file_stem()?.to_string_lossy().to_lowercase();
+    let is_named_preview = PREVIEW_NAMES.iter().any(|name| stem.contains(name));
+
+    return Some((!is_named_preview, path.components().count()));
+}
 
+/// Finds a preview image for each top-level dir of a zip archive
+/// Returns a map of dir names to PNG data
+fn find_folder_previews(archive: &File) -> Result<HashMap<String, Vec<u8>>, ExtractionError> {
     let mut archive = ZipArchive::new(archive)?;
-    let total_files = archive.len();
 
-    for i in 0..total_files {
-        let mut file = archive.by_index(i)?;
+    // Pick the best image in each dir
+    let mut candidates: HashMap<String, ((bool, usize), String)> = HashMap::new();
+    for name in archive.file_names() {
+        let path = Path::new(name);
+        let (top_level_dir, rank) = match (get_top_level_dir(path, name.ends_with('/')), get_preview_rank(path)) {
+            (Some(dir), Some(rank)) => (dir, rank),
+            _ => continue
+        };
+        let is_better = candidates.get(&top_level_dir)
+            .map_or(true, |(best_rank, _)| rank < *best_rank);
+        if is_better {
+            candidates.insert(top_level_dir, (rank, name.to_string()));
+        }
+    }
 
-        let file_path = file.enclosed_name()
-            .ok_or(ExtractionError::UnsafeFilepath(file.name().to_string()))?;
+    let mut previews = HashMap::new();
+    for (dir, (_, name)) in candidates {
+        let mut file = archive.by_name(&name)?;
+        if file.size() > MAX_PREVIEW_SIZE {
+            continue;
+        }
+        let mut data = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut data)?;
+        previews.insert(dir, data);
+    }
 
-        let extraction_path = destination.join(file_path);
+    return Ok(previews);
+}
 
-        // Extract the dir
-        if file.is_dir() {
-            create_dir_all(&extraction_path)?;
-        }
-        // Extract the file
-        else {
-            // Create the parent dir if needed
-            if let Some(parent_dir) = extraction_path.parent() {
-                if !parent_dir.exists() {
-                    create_dir_all(parent_dir)?;
-                }
-            }
-            // Create the file and write to it
-            let mut outfile = File::create(&extraction_path)?;
-            io::copy(&mut file, &mut outfile)?;
+/// Asks the user which top-level dirs of the archive they want to extract
+/// Blocks until the user answers, returns None if we should extract everything
+/// or the user has aborted the installation
+fn ask_folders_selection(
+    sender: Sender<Message>,
+    app_state: &ThreadSafeState,
+    component_id: usize,
+    archive: &File
+) -> Result<Option<HashSet<String>>, ExtractionError> {
+    let folders = list_top_level_dirs(archive)?;
+    if folders.is_empty() {
+        return Ok(None);
+    }
+
+    // Previews are nice to have, don't fail the installation because of them
+    let previews = find_folder_previews(archive).unwrap_or_else(
+        |e| {
+            eprintln!("Failed to load folder previews: {e}");
+            HashMap::new()
         }
+    );
 
-        // Update progres bar
-        let pb_val = (i as f64 + 1.0) / total_files as f64;
-        sender.send(Message::UpdateProgressBar(pb_val));
+    app_state.lock().unwrap().set_folder_choices(folders, previews);
+    sender.send(Message::SelectFolders(component_id));
 
-        // See if we want to abort
-        if app_state.lock().unwrap().get_abort_flag() {
-            return Ok(());
+    let token = app_state.lock().unwrap().get_cancel_token();
+    loop {
+        thread::sleep(POLL_DURATION);
+        let mut app_state = app_state.lock().unwrap();
+        let selection = match token.is_cancelled() {
+            true => Some(None),
+            false => app_state.take_chosen_folders().map(Some)
+        };
+        if let Some(selection) = selection {
+            app_state.clear_folder_choices();
+            return Ok(selection);
         }
     }
-    return Ok(());
 }
 
-
-/// Creates a temp dir for the installer temp data
-fn create_temp_dir() -> Result<tempfile::TempDir, io::Error> {
-    return tempfile::Builder::new()
-        .prefix(".mas_installer-")
-        .tempdir();
+/// Returns true if the file from the archive should be extracted
+/// given the top-level dirs the user has selected
+fn is_file_selected(folders: Option<&HashSet<String>>, file_path: &Path, is_dir: bool) -> bool {
+    return match (folders, get_top_level_dir(file_path, is_dir)) {
+        (Some(folders), Some(top_level_dir)) => folders.contains(&top_level_dir),
+        _ => true
+    };
 }
 
-/// Creates a temp file for the installer data
-fn create_temp_file(temp_dir: &tempfile::TempDir, name: &str) -> Result<File, io::Error> {
-    let fp = temp_dir.path().join(name);
-    return File::options()
-        .write(true)
-        .read(true)
-        .create(true)
+/// Returns the total uncompressed size of the files we would extract from a zip archive
+/// NOTE: this reads the sizes from the central directory, the actual data may differ
+fn get_uncompressed_size(archive: &File, folders: Option<&HashSet<String>>) -> Result<u64, ExtractionError> {
+    let mut archive = ZipArchive::new(archive)?;
+    let mut total_size = 0;
+
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i)?;
+        let is_selected = match file.enclosed_name() {
+            Some(file_path) => is_file_selected(folders, file_path, file.is_dir()),
+            None => true
+        };
+        if is_selected {
+            total_size += file.size();
+        }
+    }
+
+    return Ok(total_size);
+}
+
+/// Checks the number of entries against the limits
+fn check_entries_count_limit(limits: &ExtractionLimits, count: usize) -> Result<(), ExtractionError> {
+    if count > limits.max_entries {
+        return Err(ExtractionError::LimitExceeded(
+            format!("{} entries, the limit is {}", count, limits.max_entries)
+        ));
+    }
+    return Ok(());
+}
+
+/// Checks a single entry against the limits
+/// total_size is the size of the entries we've checked before, it's updated by this function
+fn check_entry_limits(limits: &ExtractionLimits, file: &ZipFile, total_size: &mut u64) -> Result<(), ExtractionError> {
+    let size = file.size();
+    if size > limits.max_entry_size {
+        return Err(ExtractionError::LimitExceeded(
+            format!(
+                "'{}' is {}, the limit is {}",
+                file.name(),
+                utils::format_size(size),
+                utils::format_size(limits.max_entry_size)
+            )
+        ));
+    }
+    if size > ExtractionLimits::MIN_RATIO_CHECK_SIZE {
+        let ratio = size / file.compressed_size().max(1);
+        if ratio > limits.max_compression_ratio {
+            return Err(ExtractionError::LimitExceeded(
+                format!(
+                    "'{}' has compression ratio {}, the limit is {}",
+                    file.name(),
+                    ratio,
+                    limits.max_compression_ratio
+                )
+            ));
+        }
+    }
+
+    *total_size = total_size.saturating_add(size);
+    if *total_size > limits.max_total_size {
+        return Err(ExtractionError::LimitExceeded(
+            format!(
+                "total size is over {}, the limit is {}",
+                utils::format_size(*total_size),
+                utils::format_size(limits.max_total_size)
+            )
+        ));
+    }
+
+    return Ok(());
+}
+
+/// Checks the archive against the limits using the sizes from the central directory,
+/// only the files we would extract are taken into account
+fn check_archive_limits<R: io::Read + io::Seek>(
+    archive: &mut ZipArchive<R>,
+    limits: &ExtractionLimits,
+    folders: Option<&HashSet<String>>
+) -> Result<(), ExtractionError> {
+    check_entries_count_limit(limits, archive.len())?;
+
+    let mut total_size: u64 = 0;
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i)?;
+        let is_selected = match file.enclosed_name() {
+            Some(file_path) => is_file_selected(folders, file_path, file.is_dir()),
+            None => true
+        };
+        if is_selected {
+            check_entry_limits(limits, &file, &mut total_size)?;
+        }
+    }
+
+    return Ok(());
+}
+
+/// Checks if the file is a launcher or a Ren'Py binary that must be executable
+/// path is the path of the file within the archive
+fn is_known_executable(path: &Path) -> bool {
+    let parts: Vec<&str> = path.components()
+        .filter_map(|c| c.as_os_str().to_str())
+        .collect();
+
+    return match parts.as_slice() {
+        // The launcher script at the root of the game
+        [name] => name.ends_with(".sh"),
+        // Ren'Py binaries
+        [.., "lib", platform, _] => platform.starts_with("linux-") || platform.starts_with("darwin-"),
+        // The mac app
+        [.., "Contents", "MacOS", _] => true,
+        _ => false
+    };
+}
+
+/// Returns unix permissions for an extracted file, None if we should keep the default ones
+/// path is the path of the file within the archive, archive_mode is the mode stored in the archive
+fn get_file_mode(path: &Path, archive_mode: Option<u32>) -> Option<u32> {
+    // Never make files writable by others, and drop setuid and co
+    // The owner must be able to write, Ren'Py recompiles rpyc files and we overwrite them on reinstall
+    let mode = archive_mode.map(|mode| (mode & 0o755) | 0o200);
+
+    if is_known_executable(path) {
+        return Some(mode.unwrap_or(0o755) | 0o755);
+    }
+    return mode;
+}
+
+/// Sets unix permissions of the file
+#[cfg(unix)]
+fn set_file_mode(path: &Path, mode: u32) -> Result<(), io::Error> {
+    use std::os::unix::fs::PermissionsExt;
+    return std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode));
+}
+
+/// Sets unix permissions of the file, does nothing on this platform
+#[cfg(not(unix))]
+fn set_file_mode(_path: &Path, _mode: u32) -> Result<(), io::Error> {
+    return Ok(());
+}
+
+/// Converts a DOS timestamp from the archive into system time
+/// DOS timestamps have no timezone, they're in the local time of whoever has made the archive,
+/// we assume it's the same as ours
+fn dos_time_to_system_time(dos_time: DateTime) -> Option<SystemTime> {
+    let naive_time = NaiveDate::from_ymd_opt(dos_time.year() as i32, dos_time.month() as u32, dos_time.day() as u32)?
+        .and_hms_opt(dos_time.hour() as u32, dos_time.minute() as u32, dos_time.second() as u32)?;
+    let local_time = match Local.from_local_datetime(&naive_time) {
+        LocalResult::Single(local_time) => local_time,
+        // The time is ambiguous when DST ends, take the earliest
+        LocalResult::Ambiguous(earliest, _) => earliest,
+        // The time doesn't exist when DST starts, the clock has skipped it,
+        // so it's the same instant as the time an hour later
+        LocalResult::None => Local.from_local_datetime(&(naive_time + ChronoDuration::hours(1))).earliest()?
+    };
+
+    return Some(SystemTime::from(local_time));
+}
+
+/// Writes a file from the archive to the given path and applies its permissions and mtime
+/// The token lets us cancel the extraction of large files
+fn extract_file(
+    file: &mut ZipFile,
+    extraction_path: &Path,
+    mode: Option<u32>,
+    token: &CancellationToken
+) -> Result<(), ExtractionError> {
+    // Don't trust the declared size, the data may be crafted to inflate further
+    let declared_size = file.size();
+    let mut outfile = File::create(extraction_path)?;
+    let mut reader = CancellableReader::new(file.take(declared_size + 1), token);
+    let written = io::copy(&mut reader, &mut outfile)?;
+    drop(reader);
+    if written > declared_size {
+        return Err(ExtractionError::LimitExceeded(
+            format!("'{}' is larger than declared", file.name())
+        ));
+    }
+    drop(outfile);
+
+    // Not critical, Ren'Py will just recompile the scripts
+    if let Some(mtime) = dos_time_to_system_time(file.last_modified()) {
+        if let Err(e) = filetime::set_file_mtime(extraction_path, FileTime::from_system_time(mtime)) {
+            eprintln!("Failed to set mtime of '{}': {e}", extraction_path.display());
+        }
+    }
+
+    if let Some(mode) = mode {
+        set_file_mode(extraction_path, mode)?;
+    }
+
+    return Ok(());
+}
+
+/// Represents an extraction shared between the worker threads
+struct ExtractionJob {
+    archive_path: PathBuf,
+    destination: PathBuf,
+    folders: Option<HashSet<String>>,
+    /// Index of the next entry a worker should pick up
+    next_entry: AtomicUsize,
+    /// Number of entries the workers have finished
+    done_entries: AtomicUsize,
+    /// Set if a worker fails
+    stop_flag: AtomicBool,
+    /// Cancelled on abort
+    token: CancellationToken,
+    /// Records the files we write
+    journal: FileJournal
+}
+
+impl ExtractionJob {
+    /// Creates a new job
+    fn new(
+        archive_path: &Path,
+        destination: &Path,
+        folders: Option<&HashSet<String>>,
+        token: CancellationToken,
+        journal: FileJournal
+    ) -> Self {
+        return Self {
+            archive_path: archive_path.to_path_buf(),
+            destination: destination.to_path_buf(),
+            folders: folders.cloned(),
+            next_entry: AtomicUsize::new(0),
+            done_entries: AtomicUsize::new(0),
+            stop_flag: AtomicBool::new(false),
+            token,
+            journal
+        };
+    }
+
+    /// Tells the workers to stop after their current entry
+    fn stop(&self) {
+        self.stop_flag.store(true, AtomicOrdering::Relaxed);
+    }
+
+    /// Checks if the workers should stop
+    fn is_stopped(&self) -> bool {
+        return self.stop_flag.load(AtomicOrdering::Relaxed) || self.token.is_cancelled();
+    }
+
+    /// Returns the number of finished entries
+    fn get_done_entries(&self) -> usize {
+        return self.done_entries.load(AtomicOrdering::Relaxed);
+    }
+
+    /// Extracts a single entry
+    /// Returns the path of the extracted file, None for dirs and skipped entries
+    fn extract_entry(&self, file: &mut ZipFile) -> Result<Option<PathBuf>, ExtractionError> {
+        let file_path = file.enclosed_name()
+            .ok_or(ExtractionError::UnsafeFilepath(file.name().to_string()))?
+            .to_path_buf();
+
+        // Skip the dirs the user doesn't want
+        if !is_file_selected(self.folders.as_ref(), &file_path, file.is_dir()) {
+            return Ok(None);
+        }
+
+        let extraction_path = self.destination.join(&file_path);
+
+        // Extract the dir
+        if file.is_dir() {
+            self.journal.before_create_dir(&extraction_path);
+            create_dir_all(&extraction_path)?;
+            return Ok(None);
+        }
+
+        // Create the parent dir if needed,
+        // create_dir_all is fine with other threads creating the same dirs
+        if let Some(parent_dir) = extraction_path.parent() {
+            if !parent_dir.exists() {
+                self.journal.before_create_dir(parent_dir);
+                create_dir_all(parent_dir)?;
+            }
+        }
+        // Create the file and write to it
+        let mode = get_file_mode(&file_path, file.unix_mode());
+        self.journal.before_write(&extraction_path)?;
+        extract_file(file, &extraction_path, mode, &self.token)?;
+
+        return Ok(Some(extraction_path));
+    }
+
+    /// Picks up entries until there's none left, this is what a worker runs
+    /// Each worker has its own handle to the archive
+    /// Returns the paths of the extracted files
+    fn run(&self) -> Result<Vec<PathBuf>, ExtractionError> {
+        let mut extracted_files = Vec::new();
+
+        let mut archive = ZipArchive::new(File::open(&self.archive_path)?)?;
+        while !self.is_stopped() {
+            let i = self.next_entry.fetch_add(1, AtomicOrdering::Relaxed);
+            if i >= archive.len() {
+                break;
+            }
+
+            let rv = archive.by_index(i)
+                .map_err(ExtractionError::from)
+                .and_then(|mut file| self.extract_entry(&mut file));
+            match rv {
+                Ok(Some(path)) => extracted_files.push(path),
+                Ok(None) => {},
+                Err(e) => {
+                    // No point to continue
+                    self.stop();
+                    return Err(e);
+                }
+            };
+            self.done_entries.fetch_add(1, AtomicOrdering::Relaxed);
+        }
+
+        return Ok(extracted_files);
+    }
+}
+
+/// Returns the number of threads to extract an archive with
+fn get_extraction_threads_count(total_files: usize) -> usize {
+    let cpus = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let max_threads = (total_files / MIN_ENTRIES_PER_THREAD).max(1);
+
+    return cpus.min(max_threads).min(MAX_EXTRACTION_THREADS);
+}
+
+/// Extracts a zip archive using a pool of worker threads
+/// If folders is set, only extracts the files from these top-level dirs
+/// (and the files at the root of the archive)
+/// The journal records the written files as we go, so they can be rolled back even if we fail
+/// Returns the paths of the extracted files
+fn extract_archive(
+    sender: Sender<Message>,
+    app_state: &ThreadSafeState,
+    archive_path: &Path,
+    destination: &Path,
+    folders: Option<&HashSet<String>>,
+    journal: &FileJournal
+) -> Result<Vec<PathBuf>, ExtractionError> {
+    sender.send(Message::UpdateProgressBar(0.0));
+
+    let mut extracted_files = Vec::new();
+
+    let (token, limits) = {
+        let app_state = app_state.lock().unwrap();
+        (app_state.get_cancel_token(), app_state.get_config().extraction_limits.clone())
+    };
+    if token.is_cancelled() {
+        return Ok(extracted_files);
+    }
+
+    let total_files = {
+        let mut archive = ZipArchive::new(File::open(archive_path)?)?;
+        check_archive_limits(&mut archive, &limits, folders)?;
+        archive.len()
+    };
+
+    let job = Arc::new(ExtractionJob::new(archive_path, destination, folders, token, journal.clone()));
+    let th_handles: Vec<thread::JoinHandle<_>> = (0..get_extraction_threads_count(total_files))
+        .map(
+            |_| {
+                let job = job.clone();
+                thread::spawn(move || job.run())
+            }
+        )
+        .collect();
+
+    // Report the progress while the workers are busy,
+    // they check the token themselves
+    while !th_handles.iter().all(|h| h.is_finished()) {
+        thread::sleep(POLL_DURATION);
+
+        let pb_val = job.get_done_entries() as f64 / total_files.max(1) as f64;
+        sender.send(Message::UpdateProgressBar(pb_val));
+    }
+
+    // Collect the results, return the first error if any
+    let mut rv = Ok(());
+    for th_handle in th_handles {
+        match th_handle.join() {
+            Ok(Ok(files)) => extracted_files.extend(files),
+            Ok(Err(e)) => {
+                if rv.is_ok() {
+                    rv = Err(e);
+                }
+            },
+            Err(e) => {
+                eprintln!("Extraction thread has panicked {:?}", e);
+                if rv.is_ok() {
+                    rv = Err(ExtractionError::IOError(io::Error::new(io::ErrorKind::Other, "extraction thread has panicked")));
+                }
+            }
+        };
+    }
+    rv?;
+
+    sender.send(Message::UpdateProgressBar(1.0));
+
+    return Ok(extracted_files);
+}
+
+
+/// Wraps a download stream to report the progress
+struct ProgressReader<R: Read> {
+    inner: R,
+    sender: Sender<Message>,
+    // None if the size is unknown, then the bar only shows that we're getting data
+    content_size: Option<ContentSize>,
+    total_read: ContentSize,
+    last_reported: ContentSize
+}
+
+impl<R: Read> ProgressReader<R> {
+    /// Creates a new reader, content_size is the expected size of the stream
+    fn new(inner: R, sender: Sender<Message>, content_size: Option<ContentSize>) -> Self {
+        return Self { inner, sender, content_size, total_read: 0, last_reported: 0 };
+    }
+
+    /// Sets the amount of data we had before this stream
+    fn with_offset(mut self, offset: ContentSize) -> Self {
+        self.total_read = offset;
+        self.last_reported = offset;
+        return self;
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.total_read += n as ContentSize;
+
+        // Don't flood the UI with messages
+        let step = match self.content_size {
+            Some(content_size) => (content_size / 1000).max(1),
+            None => PULSE_PROGRESS_STEP
+        };
+        if self.total_read - self.last_reported >= step {
+            match self.content_size {
+                Some(content_size) => {
+                    let pb_val = self.total_read as f64 / content_size as f64;
+                    self.sender.send(Message::UpdateProgressBar(pb_val.min(1.0)));
+                },
+                None => self.sender.send(Message::PulseProgressBar(self.total_read))
+            };
+            self.last_reported = self.total_read;
+        }
+
+        return Ok(n);
+    }
+}
+
+/// Downloads a zip archive and extracts it on the fly,
+/// the entries are read from their local headers as the data arrives,
+/// at the end the central directory is checked against what we've extracted
+/// Returns the paths of the extracted files
+fn stream_archive(
+    client: &reqwest::Client,
+    sender: Sender<Message>,
+    app_state: &ThreadSafeState,
+    download_link: &str,
+    content_size: ContentSize,
+    destination: &Path,
+    journal: &FileJournal
+) -> Result<Vec<PathBuf>, InstallError> {
+    sender.send(Message::UpdateProgressBar(0.0));
+
+    let mut extracted_files = Vec::new();
+
+    let (token, limits, limiter, stall_timeout) = {
+        let app_state = app_state.lock().unwrap();
+        let config = app_state.get_config();
+        (
+            app_state.get_cancel_token(),
+            config.extraction_limits.clone(),
+            RateLimiter::new(config.get_download_speed_limit()),
+            config.timeouts.get_stall()
+        )
+    };
+    if token.is_cancelled() {
+        return Ok(extracted_files);
+    }
+
+    let resp = send_request(&token, client.get(download_link))?;
+    let status_code = resp.status();
+    if !status_code.is_success() {
+        return Err(DownloadError::InvalidStatusCode(status_code).into());
+    }
+    let mut reader = ProgressReader::new(
+        // We can't retry a part of the stream, but at least we don't hang on a dead connection
+        ThrottledReader::new(AbortableReader::new(resp, &token).with_stall_timeout(stall_timeout), &limiter, &token),
+        sender,
+        Some(content_size)
+    );
+
+    let mut entries: Vec<EntryInfo> = Vec::new();
+    // Paths of the entries within the archive, None for dirs
+    let mut entries_paths: Vec<Option<PathBuf>> = Vec::new();
+    let mut total_size: u64 = 0;
+
+    loop {
+        let mut file = match read_zipfile_from_stream(&mut reader).map_err(ExtractionError::from)? {
+            Some(file) => file,
+            // Reached the central directory
+            None => break
+        };
+
+        check_entries_count_limit(&limits, entries.len() + 1)?;
+        check_entry_limits(&limits, &file, &mut total_size)?;
+
+        let file_path = file.enclosed_name()
+            .ok_or(ExtractionError::UnsafeFilepath(file.name().to_string()))?
+            .to_path_buf();
+        let extraction_path = destination.join(&file_path);
+
+        // Extract the dir
+        if file.is_dir() {
+            journal.before_create_dir(&extraction_path);
+            create_dir_all(&extraction_path)?;
+            entries_paths.push(None);
+        }
+        // Extract the file
+        else {
+            if let Some(parent_dir) = extraction_path.parent() {
+                if !parent_dir.exists() {
+                    journal.before_create_dir(parent_dir);
+                    create_dir_all(parent_dir)?;
+                }
+            }
+            // Local headers don't have permissions, we use the fallback rules for now
+            let mode = get_file_mode(&file_path, None);
+            journal.before_write(&extraction_path)?;
+            extract_file(&mut file, &extraction_path, mode, &token)?;
+            extracted_files.push(extraction_path);
+            entries_paths.push(Some(file_path));
+        }
+        entries.push(EntryInfo::from_zip_file(&file));
+
+        // See if we want to abort
+        if token.is_cancelled() {
+            return Ok(extracted_files);
+        }
+    }
+
+    // The rest of the stream is the central directory,
+    // the signature has already been consumed
+    let mut central_dir = CENTRAL_DIR_HEADER_SIGNATURE.to_le_bytes().to_vec();
+    (&mut reader).take(MAX_CENTRAL_DIR_SIZE).read_to_end(&mut central_dir)?;
+
+    let central_dir_entries = streaming::parse_central_dir(&central_dir)?;
+    streaming::validate_entries(&entries, &central_dir_entries)?;
+
+    // Now we can apply the permissions
+    for (file_path, central_dir_entry) in entries_paths.iter().zip(central_dir_entries.iter()) {
+        if let (Some(file_path), Some(_)) = (file_path, central_dir_entry.unix_mode) {
+            if let Some(mode) = get_file_mode(file_path, central_dir_entry.unix_mode) {
+                set_file_mode(&destination.join(file_path), mode)?;
+            }
+        }
+    }
+
+    sender.send(Message::UpdateProgressBar(1.0));
+
+    return Ok(extracted_files);
+}
+
+
+/// Downloads the archive of the component into a temp file of the session,
+/// continues the download if we've started it before
+fn download_component(
+    client: &reqwest::Client,
+    sender: Sender<Message>,
+    app_state: &ThreadSafeState,
+    session: &mut InstallSession,
+    component_id: &str,
+    asset: &GHAsset
+) -> InstallResult {
+    let url = &asset.browser_download_url;
+    if session.get_download(url).is_none() {
+        let temp_file = create_temp_file(session.get_temp_dir(), &format!("{}.tmp", component_id))?;
+        session.add_download(url, temp_file);
+    }
+    let temp_file = session.get_download_mut(url).unwrap();
+
+    download_to_file(
+        client,
+        sender,
+        app_state,
+        url,
+        Some(asset.size),
+        &mut temp_file.file
+    )?;
+    if !app_state.lock().unwrap().get_cancel_token().is_cancelled() {
+        session.set_download_complete(url);
+    }
+
+    return Ok(());
+}
+
+/// Creates a temp file for the installer data
+fn create_temp_file(temp_dir: &tempfile::TempDir, name: &str) -> Result<TempFile, io::Error> {
+    let fp = temp_dir.path().join(name);
+    let file = File::options()
+        .write(true)
+        .read(true)
+        .create(true)
         .truncate(true)
-        .open(&fp);
+        .open(&fp)?;
+    return Ok(TempFile { file, path: fp });
+}
+
+
+/// Checks if all files of the archive are within the game folder
+/// (the archive has the game/ dir at its root)
+fn has_game_root(archive: &File) -> Result<bool, ExtractionError> {
+    let archive = ZipArchive::new(archive)?;
+    let has_files = !archive.is_empty();
+    return Ok(has_files && archive.file_names().all(|name| name.starts_with("game/")));
+}
+
+/// Installs, updates and removes submods according to the user selection
+fn install_submods(
+    client: &reqwest::Client,
+    sender: Sender<Message>,
+    app_state: &ThreadSafeState,
+    session: &InstallSession,
+    game_dir: &Path
+) -> InstallResult {
+    let (catalog, available_submods, selected_submods, token, github_token) = {
+        let app_state = app_state.lock().unwrap();
+        match app_state.get_submods_catalog() {
+            Some(catalog) => (
+                catalog.clone(),
+                app_state.get_available_submod_ids(),
+                app_state.get_selected_submods().clone(),
+                app_state.get_cancel_token(),
+                app_state.get_config().get_github_token().map(str::to_string)
+            ),
+            None => return Ok(())
+        }
+    };
+    let journal = session.get_journal();
+    let mut record = SubmodsRecord::load(game_dir);
+
+    // Remove the submods the user has deselected,
+    // the incompatible ones aren't shown to the user, so we don't touch them
+    let removed_submods: Vec<InstalledSubmod> = catalog.submods.iter()
+        .filter(|s| available_submods.contains(&s.id) && !selected_submods.contains(&s.id))
+        .filter_map(|s| record.remove(&s.id))
+        .collect();
+    if !removed_submods.is_empty() {
+        sender.send(Message::RemovingSubmods);
+        for submod in removed_submods.iter() {
+            remove_submod_files(game_dir, submod, &[], journal);
+        }
+        record.save(game_dir, journal)?;
+    }
+
+    for (i, submod) in catalog.submods.iter().enumerate() {
+        if !available_submods.contains(&submod.id) || !selected_submods.contains(&submod.id) {
+            continue;
+        }
+
+        sender.send(Message::DownloadingSubmod(i));
+        let release = {
+            let client = client.clone();
+            let repo = submod.repo.clone();
+            let github_token = github_token.clone();
+            run_cancellable(&token, move || fetch_repo_release(&client, github_token.as_deref(), &repo))??
+        };
+        if record.get(&submod.id).map(|s| s.version == release.tag_name).unwrap_or(false) {
+            println!("Submod '{}' is up to date", submod.id);
+            continue;
+        }
+
+        let asset_re = match submod.asset_pattern {
+            Some(ref pattern) => Some(
+                Regex::new(pattern)
+                    .map_err(|_| InstallError::CorruptedJSON("Found a submod with invalid asset pattern"))?
+            ),
+            None => None
+        };
+        let asset = release.assets.iter()
+            .filter(|a| a.is_valid())
+            .find(|a| asset_re.as_ref().map(|re| re.is_match(&a.name)).unwrap_or(true))
+            .ok_or(InstallError::CorruptedJSON("A submod release has no suitable asset"))?;
+
+        let mut temp_file = create_temp_file(session.get_temp_dir(), &format!("submod_{}.tmp", submod.id))?;
+        download_to_file(
+            client,
+            sender,
+            app_state,
+            &asset.browser_download_url,
+            Some(asset.size),
+            &mut temp_file.file
+        )?;
+        if token.is_cancelled() {
+            return Ok(());
+        }
+
+        sender.send(Message::ExtractingSubmod(i));
+        let destination = match has_game_root(&temp_file.file)? {
+            true => game_dir.to_path_buf(),
+            false => game_dir.join(SUBMODS_DIR_PATH)
+        };
+        let files: Vec<PathBuf> = extract_archive(sender, app_state, &temp_file.path, &destination, None, journal)?
+            .into_iter()
+            .filter_map(|p| p.strip_prefix(game_dir).ok().map(Path::to_path_buf))
+            .collect();
+
+        // Remove the files the new version doesn't have anymore
+        if let Some(old_submod) = record.get(&submod.id) {
+            remove_submod_files(game_dir, old_submod, &files, journal);
+        }
+        record.insert(
+            InstalledSubmod {
+                id: submod.id.clone(),
+                version: release.tag_name.clone(),
+                files
+            }
+        );
+        // Save after each submod so we don't lose track if something fails
+        record.save(game_dir, journal)?;
+
+        if token.is_cancelled() {
+            return Ok(());
+        }
+        sleep();
+    }
+
+    return Ok(());
 }
 
 
 /// This runs cleanup logic on SUCCESSFUL download
-fn cleanup(sender: Sender<Message>, mas_temp_file: File, spr_temp_file: File) {
+fn cleanup(sender: Sender<Message>, session: InstallSession) {
     sender.send(Message::CleaningUp);
     sender.send(Message::UpdateProgressBar(0.0));
-    drop(mas_temp_file);
-    drop(spr_temp_file);
+    drop(session);
     sleep();
     sender.send(Message::UpdateProgressBar(1.0));
     sleep();
@@ -412,122 +1804,250 @@ fn cleanup(sender: Sender<Message>, mas_temp_file: File, spr_temp_file: File) {
 
 
 /// Main method to handle game installation process, downloads it into a temp folder and then extracts
+/// The steps we've done are saved in the session, so we can continue from the failed one
 pub fn install_game(
     sender: Sender<Message>,
-    app_state: &ThreadSafeState
+    app_state: &ThreadSafeState,
+    saved_session: &mut Option<InstallSession>
 ) -> InstallResult {
     sender.send(Message::Preparing);
     sender.send(Message::UpdateProgressBar(0.0));
 
-    if app_state.lock().unwrap().get_abort_flag() {
+    let (token, config) = {
+        let app_state = app_state.lock().unwrap();
+        (app_state.get_cancel_token(), app_state.get_config().clone())
+    };
+    if token.is_cancelled() {
         return Ok(());
     }
 
-    let client = build_client()?;
+    let client = build_client(&config)?;
 
-    // Get download link
-    let data = get_release_data(&client)?;
-    let main_asset = match app_state.lock().unwrap().get_deluxe_ver_flag() {
-        true => data.dlx_ver_asset,
-        false => data.def_ver_asset
+    // Get download links
+    let data = {
+        let client = client.clone();
+        let github_token = config.get_github_token().map(str::to_string);
+        run_cancellable(&token, move || get_release_data(&client, github_token.as_deref()))??
+    };
+    let (ddlc_dir, selected_components) = {
+        let mut app_state = app_state.lock().unwrap();
+        // The user has chosen from the components they saw, we can't map the choice onto different ones
+        if data.get_components() != app_state.get_components() {
+            app_state.set_release_data(Some(data));
+            sender.send(Message::ReleaseDataLoaded);
+            return Err(InstallError::ReleaseChanged);
+        }
+        app_state.set_release_data(Some(data.clone()));
+        (app_state.get_extraction_dir().clone(), app_state.get_selected_components().clone())
     };
-    let mut destination = app_state.lock().unwrap().get_extraction_dir().clone();
-    // Since mac is pain, we have to adjust the destination to be
-    // within the app
-    if env::consts::OS == "macos" {
-        destination.push("Contents/Resources/autorun");
-    }
 
     sender.send(Message::UpdateProgressBar(0.5));
     sleep();
 
-    // Create temp structures
-    let temp_dir = create_temp_dir()?;
-    let mut mas_temp_file = create_temp_file(&temp_dir, "mas.tmp")?;
-    let mut spr_temp_file = create_temp_file(&temp_dir, "spr.tmp")?;
-
-    // Remove old rpy/rpyc
-    // Yeah...some people have rpy in the base dir...
-    prepare_ddlc_dir(&destination, true, false);
-    prepare_ddlc_dir(&destination.join("game"), true, false);
+    // Start a new session unless we're retrying an installation into the same dir
+    if !matches!(*saved_session, Some(ref session) if session.is_for(&ddlc_dir)) {
+        *saved_session = Some(InstallSession::new(&ddlc_dir)?);
+    }
+    // Safe to unwrap, we've just set it
+    let session = saved_session.as_mut().unwrap();
+
+    let game_dir = get_game_dir(&ddlc_dir);
+    // Components we extract while downloading, the rest are downloaded into temp files first
+    // We need the whole archive to let the user choose folders
+    let (streamed_components, components): (IndexedComponents, IndexedComponents) = data.get_components()
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| selected_components.contains(&c.id))
+        .partition(|(_, c)| config.streaming_install && !c.selective);
+
+    // Check we can download everything and write into the destinations
+    let mut report = PreflightReport::default();
+    // Safe to unwrap, parse_release guarantees all components have assets
+    let download_size = components.iter()
+        .map(|(_, c)| data.get_asset(&c.id).unwrap())
+        .filter(|a| !session.is_download_complete(&a.browser_download_url))
+        .map(|a| a.size)
+        .sum();
+    report.check_space(session.get_temp_dir().path(), download_size);
+    report.check_writable(&game_dir);
+    for (_, component) in components.iter().chain(streamed_components.iter()) {
+        report.check_writable(&component.get_destination(&ddlc_dir));
+    }
+    report.verify()?;
 
     sender.send(Message::UpdateProgressBar(1.0));
     sleep();
 
-    // Install MAS
-    sender.send(Message::Downloading);
-    download_to_file(
-        &client,
-        sender,
-        app_state,
-        &main_asset.browser_download_url,
-        Some(main_asset.size),
-        &mut mas_temp_file
-    )?;
-    if app_state.lock().unwrap().get_abort_flag() {
-        return Ok(());
+    // Download everything before we touch the game files
+    for (i, component) in components.iter() {
+        let asset = data.get_asset(&component.id).unwrap();
+        if session.is_download_complete(&asset.browser_download_url) {
+            println!("Reusing the downloaded '{}'...", component.id);
+            continue;
+        }
+
+        sender.send(Message::Downloading(*i));
+        download_component(&client, sender, app_state, session, &component.id, asset)?;
+        if token.is_cancelled() {
+            return Ok(());
+        }
+        sleep();
     }
-    sleep();
 
-    sender.send(Message::Extracting);
-    extract_archive(
-        sender,
-        app_state,
-        &mas_temp_file,
-        &destination
-    )?;
-    if app_state.lock().unwrap().get_abort_flag() {
-        return Ok(());
+    // Ask the user which folders they want
+    for (i, component) in components.iter() {
+        if !component.selective || session.get_chosen_folders(&component.id).is_some() {
+            continue;
+        }
+        let url = &data.get_asset(&component.id).unwrap().browser_download_url;
+        let folders = ask_folders_selection(sender, app_state, *i, &session.get_download(url).unwrap().file)?;
+        if token.is_cancelled() {
+            return Ok(());
+        }
+        session.set_chosen_folders(&component.id, folders);
     }
+    let components_folders: Vec<Option<HashSet<String>>> = components.iter()
+        .map(|(_, c)| session.get_chosen_folders(&c.id).cloned().flatten())
+        .collect();
+
+    // Check we have space for the extracted files
+    let mut extracted_size = 0;
+    for ((_, component), folders) in components.iter().zip(components_folders.iter()) {
+        if session.is_step_done(&InstallStep::Extract(component.id.clone())) {
+            continue;
+        }
+        let url = &data.get_asset(&component.id).unwrap().browser_download_url;
+        let archive = &session.get_download(url).unwrap().file;
+        // Check the limits before we touch the game files
+        check_archive_limits(
+            &mut ZipArchive::new(archive).map_err(ExtractionError::from)?,
+            &config.extraction_limits,
+            folders.as_ref()
+        )?;
+        extracted_size += get_uncompressed_size(archive, folders.as_ref())?;
+    }
+    // We don't know the uncompressed size of the streamed components yet,
+    // so the download size is the best estimate we have
+    extracted_size += streamed_components.iter()
+        .filter(|(_, c)| !session.is_step_done(&InstallStep::Stream(c.id.clone())))
+        .map(|(_, c)| data.get_asset(&c.id).unwrap().size)
+        .sum::<u64>();
+    report.check_space(&game_dir, extracted_size);
+    report.verify()?;
+
+    sender.send(Message::Preparing);
+    sender.send(Message::UpdateProgressBar(0.0));
+
+    if !session.is_step_done(&InstallStep::PrepareDir) {
+        prepare_ddlc_dir(&game_dir, session.get_journal());
+        session.set_step_done(InstallStep::PrepareDir);
+    }
+
+    sender.send(Message::UpdateProgressBar(1.0));
     sleep();
 
-    // Quit early if the user doesn't want spritepacks
-    if !app_state.lock().unwrap().get_install_spr_flag() {
-        cleanup(sender, mas_temp_file, spr_temp_file);
-        return Ok(());
+    for (j, (i, component)) in components.iter().enumerate() {
+        let step = InstallStep::Extract(component.id.clone());
+        if session.is_step_done(&step) {
+            continue;
+        }
+        let url = &data.get_asset(&component.id).unwrap().browser_download_url;
+
+        sender.send(Message::Extracting(*i));
+        extract_archive(
+            sender,
+            app_state,
+            &session.get_download(url).unwrap().path,
+            &component.get_destination(&ddlc_dir),
+            components_folders[j].as_ref(),
+            session.get_journal()
+        )?;
+        if token.is_cancelled() {
+            return Ok(());
+        }
+        session.set_step_done(step);
+        sleep();
     }
 
-    // We don't want to add spritepacks inside the app, so find the parent dir
-    // and extract there
-    if env::consts::OS == "macos" {
-        for _ in 0..4 {
-            destination.pop();
+    for (i, component) in streamed_components.iter() {
+        let step = InstallStep::Stream(component.id.clone());
+        if session.is_step_done(&step) {
+            continue;
         }
+        let asset = data.get_asset(&component.id).unwrap();
+
+        sender.send(Message::Streaming(*i));
+        let rv = stream_archive(
+            &client,
+            sender,
+            app_state,
+            &asset.browser_download_url,
+            asset.size,
+            &component.get_destination(&ddlc_dir),
+            session.get_journal()
+        );
+        match rv {
+            // zip can't stream some archives (e.g. with data descriptors), but can extract them from a file
+            Err(InstallError::ExtractionError(ExtractionError::ArchiveError(ZipError::UnsupportedArchive(reason)))) => {
+                eprintln!("Can't extract '{}' while downloading ({reason}), downloading it first...", component.id);
+                let url = &asset.browser_download_url;
+                if !session.is_download_complete(url) {
+                    sender.send(Message::Downloading(*i));
+                    download_component(&client, sender, app_state, session, &component.id, asset)?;
+                    if token.is_cancelled() {
+                        return Ok(());
+                    }
+                }
+
+                sender.send(Message::Extracting(*i));
+                extract_archive(
+                    sender,
+                    app_state,
+                    &session.get_download(url).unwrap().path,
+                    &component.get_destination(&ddlc_dir),
+                    None,
+                    session.get_journal()
+                )?;
+            },
+            rv => {
+                rv?;
+            }
+        };
+        if token.is_cancelled() {
+            return Ok(());
+        }
+        session.set_step_done(step);
+        sleep();
     }
 
-    // Install spritepacks
-    sender.send(Message::DownloadingSpr);
-    download_to_file(
-        &client,
-        sender,
-        app_state,
-        &data.spr_asset.browser_download_url,
-        Some(data.spr_asset.size),
-        &mut spr_temp_file
-    )?;
-    if app_state.lock().unwrap().get_abort_flag() {
-        return Ok(());
+    if !session.is_step_done(&InstallStep::Submods) {
+        install_submods(&client, sender, app_state, session, &game_dir)?;
+        if token.is_cancelled() {
+            return Ok(());
+        }
+        app_state.lock().unwrap().reload_installed_submods();
+        session.set_step_done(InstallStep::Submods);
     }
-    sleep();
 
-    sender.send(Message::ExtractingSpr);
-    extract_archive(
-        sender,
-        app_state,
-        &spr_temp_file,
-        &destination.join("spritepacks")
-    )?;
-    if app_state.lock().unwrap().get_abort_flag() {
-        return Ok(());
+    if !session.is_step_done(&InstallStep::DesktopEntries) {
+        let (menu_entry, desktop_shortcut) = app_state.lock().unwrap().get_desktop_entry_flags();
+        // This is "best-effort", the game is installed anyway
+        let rv = desktop_entry::create_entries(&ddlc_dir, menu_entry, desktop_shortcut, session.get_journal());
+        if let Err(e) = rv {
+            eprintln!("Failed to create the desktop entries: {e}");
+        }
+        session.set_step_done(InstallStep::DesktopEntries);
     }
-    sleep();
 
-    cleanup(sender, mas_temp_file, spr_temp_file);
+    // Safe to unwrap, we've set the session above
+    cleanup(sender, saved_session.take().unwrap());
 
     return Ok(());
 }
 
 /// Threaded version of install_game
+/// If the installation fails or gets aborted, the session is saved into the app state
+/// so it can be retried or rolled back
 pub fn install_game_in_thread(
     sender: Sender<Message>,
     app_state: &ThreadSafeState
@@ -537,7 +2057,21 @@ pub fn install_game_in_thread(
 
     return thread::spawn(
         move || -> InstallResult {
-            return match install_game(sender, &app_state) {
+            let (token, mut session) = {
+                let mut app_state = app_state.lock().unwrap();
+                (app_state.get_cancel_token(), app_state.take_install_session())
+            };
+            let rv = install_game(sender, &app_state, &mut session);
+            // It's None if we're done
+            app_state.lock().unwrap().set_install_session(session);
+
+            return match rv {
+                // Cancelled operations fail, but the user has aborted anyway
+                Err(_) if token.is_cancelled() => Ok(()),
+                Err(e @ InstallError::ReleaseChanged) => {
+                    sender.send(Message::ReleaseChanged);
+                    Err(e)
+                },
                 Err(e) => {
                     sender.send(Message::Error);
                     Err(e)
@@ -547,3 +2081,139 @@ pub fn install_game_in_thread(
         }
     );
 }
+
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::{write::FileOptions, ZipWriter};
+
+    /// Builds an archive in memory with the given files and their unix modes
+    pub(crate) fn build_archive(files: &[(&str, u32)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, mode) in files {
+            writer.start_file(*name, FileOptions::default().unix_permissions(*mode)).unwrap();
+            writer.write_all(b"#!/bin/sh\n").unwrap();
+        }
+        return writer.finish().unwrap().into_inner();
+    }
+
+    /// Extracts the file from the archive into a temp dir and returns its mode
+    #[cfg(unix)]
+    fn extract_and_get_mode(files: &[(&str, u32)], name: &str) -> u32 {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut archive = ZipArchive::new(Cursor::new(build_archive(files))).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extraction_path = temp_dir.path().join("file");
+
+        let mut file = archive.by_name(name).unwrap();
+        let mode = get_file_mode(Path::new(name), file.unix_mode());
+        extract_file(&mut file, &extraction_path, mode, &CancellationToken::new()).unwrap();
+
+        return std::fs::metadata(&extraction_path).unwrap().permissions().mode() & 0o777;
+    }
+
+    #[test]
+    fn test_known_executables() {
+        assert!(is_known_executable(Path::new("DDLC.sh")));
+        assert!(is_known_executable(Path::new("lib/linux-x86_64/DDLC")));
+        assert!(is_known_executable(Path::new("lib/linux-i686/python")));
+        assert!(is_known_executable(Path::new("lib/darwin-x86_64/DDLC")));
+        assert!(is_known_executable(Path::new("DDLC.app/Contents/MacOS/DDLC")));
+
+        assert!(!is_known_executable(Path::new("game/script.rpy")));
+        assert!(!is_known_executable(Path::new("game/tools/run.sh")));
+        assert!(!is_known_executable(Path::new("lib/windows-i686/DDLC.exe")));
+        assert!(!is_known_executable(Path::new("lib/linux-x86_64/lib/python2.7/os.py")));
+    }
+
+    #[test]
+    fn test_file_mode_fallback() {
+        assert_eq!(get_file_mode(Path::new("DDLC.sh"), None), Some(0o755));
+        assert_eq!(get_file_mode(Path::new("DDLC.sh"), Some(0o100644)), Some(0o755));
+        assert_eq!(get_file_mode(Path::new("game/script.rpy"), None), None);
+        assert_eq!(get_file_mode(Path::new("game/script.rpy"), Some(0o100644)), Some(0o644));
+        // Dropping dangerous bits
+        assert_eq!(get_file_mode(Path::new("game/script.rpy"), Some(0o104777)), Some(0o755));
+        // Keeping the files writable by the owner
+        assert_eq!(get_file_mode(Path::new("game/script.rpyc"), Some(0o100444)), Some(0o644));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_extract_preserves_mode() {
+        let files = [
+            ("game/tool", 0o755),
+            ("game/script.rpy", 0o644),
+            ("game/readonly.txt", 0o444)
+        ];
+        assert_eq!(extract_and_get_mode(&files, "game/tool"), 0o755);
+        assert_eq!(extract_and_get_mode(&files, "game/script.rpy"), 0o644);
+        // Read-only files become writable by the owner
+        assert_eq!(extract_and_get_mode(&files, "game/readonly.txt"), 0o644);
+    }
+
+    #[test]
+    fn test_dos_time_conversion() {
+        use chrono::{Datelike, Timelike};
+
+        let dos_time = DateTime::from_date_and_time(2022, 8, 14, 13, 45, 30).unwrap();
+        let local_time: chrono::DateTime<Local> = dos_time_to_system_time(dos_time).unwrap().into();
+        assert_eq!(
+            (local_time.year(), local_time.month(), local_time.day()),
+            (2022, 8, 14)
+        );
+        assert_eq!(
+            (local_time.hour(), local_time.minute(), local_time.second()),
+            (13, 45, 30)
+        );
+    }
+
+    #[test]
+    fn test_extract_preserves_mtime() {
+        let dos_time = DateTime::from_date_and_time(2021, 3, 7, 8, 9, 10).unwrap();
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        writer.start_file("script.rpy", FileOptions::default().last_modified_time(dos_time)).unwrap();
+        writer.write_all(b"label start:\n").unwrap();
+        let mut archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let extraction_path = temp_dir.path().join("script.rpy");
+        extract_file(&mut archive.by_name("script.rpy").unwrap(), &extraction_path, None, &CancellationToken::new()).unwrap();
+
+        let mtime = std::fs::metadata(&extraction_path).unwrap().modified().unwrap();
+        assert_eq!(mtime, dos_time_to_system_time(dos_time).unwrap());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_extract_fixes_launchers() {
+        let files = [
+            ("DDLC.sh", 0o644),
+            ("lib/linux-x86_64/DDLC", 0o600),
+            ("world_writable", 0o777)
+        ];
+        assert_eq!(extract_and_get_mode(&files, "DDLC.sh"), 0o755);
+        assert_eq!(extract_and_get_mode(&files, "lib/linux-x86_64/DDLC"), 0o755);
+        assert_eq!(extract_and_get_mode(&files, "world_writable"), 0o755);
+    }
+
+    #[test]
+    fn test_is_github_api_url() {
+        assert!(is_github_api_url("https://api.github.com/repos/owner/repo/releases/latest"));
+        assert!(!is_github_api_url("http://api.github.com/repos/owner/repo/releases/latest"));
+        assert!(!is_github_api_url("https://github.com/owner/repo/releases/download/v1/asset.zip"));
+        assert!(!is_github_api_url("https://api.github.com.example.com/catalog.json"));
+        assert!(!is_github_api_url("not a url"));
+    }
+
+    #[test]
+    fn test_parse_content_range_start() {
+        assert_eq!(parse_content_range_start("bytes 100-199/1000"), Some(100));
+        assert_eq!(parse_content_range_start("bytes 0-99/*"), Some(0));
+        assert_eq!(parse_content_range_start("bytes */1000"), None);
+        assert_eq!(parse_content_range_start("items 0-9/10"), None);
+    }
+}
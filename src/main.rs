@@ -3,15 +3,26 @@
 
 mod app;
 mod audio;
+mod bandwidth;
+mod cancellation;
+mod cache;
+mod chunking;
+mod components;
+mod config;
+mod desktop_entry;
 mod errors;
 mod installer;
+mod markdown;
+mod preflight;
+mod rollback;
+mod session;
 mod static_data;
+mod streaming;
+mod submods;
+mod summary;
 mod utils;
 
 
-use std::collections::HashMap;
-
-use regex::Regex;
 use lazy_static::lazy_static;
 use reqwest::header::{self, HeaderValue, HeaderMap};
 use const_format;
@@ -30,15 +41,6 @@ const CHANGELOG_URL: &str = const_format::formatcp!("https://github.com/{ORG_NAM
 
 
 lazy_static! {
-    /// The map of regex patterns for the release assets
-    pub static ref ASSETS_NAMES_RE_MAP: HashMap<&'static str, Regex> = {
-        let mut hm = HashMap::new();
-        hm.insert("def_ver", Regex::new(r"^Monika_After_Story-\d+\.\d+\.\d+-Mod\.zip$").unwrap());
-        hm.insert("dlx_ver", Regex::new(r"^Monika_After_Story-\d+\.\d+\.\d+-Mod-Dlx\.zip$").unwrap());
-        hm.insert("spr", Regex::new(r"^spritepacks\.zip$").unwrap());
-        hm
-    };
-
     /// The headers we use to access GH API
     pub static ref HEADERS: HeaderMap = {
         let mut h = HeaderMap::new();
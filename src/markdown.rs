@@ -0,0 +1,204 @@
+/// The module that implements rendering of release notes
+/// We only support the basic markdown, the result is plain text
+/// with a style for each byte, which is what fltk style buffers use
+
+// Style chars, they index the style table of the text display
+pub const STYLE_PLAIN: char = 'A';
+pub const STYLE_HEADING: char = 'B';
+pub const STYLE_BOLD: char = 'C';
+pub const STYLE_CODE: char = 'D';
+pub const STYLE_LINK: char = 'E';
+
+const BULLET: &str = "• ";
+const RULE: &str = "────────────────────";
+
+
+/// Text with a style char for each of its bytes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StyledText {
+    pub text: String,
+    pub style: String
+}
+
+impl StyledText {
+    /// Appends the text with the given style
+    fn push_str(&mut self, text: &str, style: char) {
+        self.text.push_str(text);
+        self.style.extend(std::iter::repeat(style).take(text.len()));
+    }
+
+    /// Appends a char with the given style
+    fn push(&mut self, c: char, style: char) {
+        self.text.push(c);
+        self.style.extend(std::iter::repeat(style).take(c.len_utf8()));
+    }
+}
+
+
+/// Returns the heading text if the line is a heading
+fn get_heading(line: &str) -> Option<&str> {
+    let text = line.trim_start_matches('#');
+    let level = line.len() - text.len();
+    if level == 0 || level > 6 || !(text.is_empty() || text.starts_with(' ')) {
+        return None;
+    }
+    return Some(text.trim().trim_end_matches('#').trim_end());
+}
+
+/// Returns the item text if the line is a list item
+fn get_list_item(line: &str) -> Option<&str> {
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(text) = line.strip_prefix(marker) {
+            return Some(text);
+        }
+    }
+    return None;
+}
+
+/// Checks if the line is a horizontal rule
+fn is_rule(line: &str) -> bool {
+    let line: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    return line.len() >= 3 && ['-', '*', '_'].iter().any(|c| line.chars().all(|lc| lc == *c));
+}
+
+/// Finds the closing marker and returns the text between
+fn find_closing<'a>(text: &'a str, marker: &str) -> Option<&'a str> {
+    let end = text.find(marker)?;
+    return match end {
+        0 => None,
+        _ => Some(&text[..end])
+    };
+}
+
+/// Parses a link at the start of the text, [text](url)
+/// Returns the text of the link and the length of the whole link
+fn parse_link(text: &str) -> Option<(&str, usize)> {
+    let rest = text.strip_prefix('[')?;
+    let text_end = rest.find("](")?;
+    let url_end = rest[text_end+2..].find(')')?;
+    return Some((&rest[..text_end], 1 + text_end + 2 + url_end + 1));
+}
+
+/// Renders the inline elements: bold, code and links
+fn render_inline(line: &str, base_style: char, rv: &mut StyledText) {
+    let mut i = 0;
+    while i < line.len() {
+        let rest = &line[i..];
+
+        if let Some(marker) = ["**", "__"].iter().find(|m| rest.starts_with(**m)) {
+            if let Some(text) = find_closing(&rest[2..], marker) {
+                rv.push_str(text, STYLE_BOLD);
+                i += text.len() + 4;
+                continue;
+            }
+        }
+        if let Some(text) = rest.strip_prefix('`').and_then(|r| find_closing(r, "`")) {
+            rv.push_str(text, STYLE_CODE);
+            i += text.len() + 2;
+            continue;
+        }
+        // Images are shown as their alt text
+        let (link, prefix_len) = match rest.strip_prefix('!') {
+            Some(image) => (parse_link(image), 1),
+            None => (parse_link(rest), 0)
+        };
+        if let Some((text, len)) = link {
+            rv.push_str(text, STYLE_LINK);
+            i += prefix_len + len;
+            continue;
+        }
+
+        // Safe to unwrap, i is within the line
+        let c = rest.chars().next().unwrap();
+        rv.push(c, base_style);
+        i += c.len_utf8();
+    }
+}
+
+/// Renders the markdown into styled text
+pub fn render(markdown: &str) -> StyledText {
+    let mut rv = StyledText::default();
+    let mut is_code_block = false;
+
+    for line in markdown.lines() {
+        let line = line.trim_end();
+        let trimmed_line = line.trim_start();
+
+        if trimmed_line.starts_with("```") {
+            is_code_block = !is_code_block;
+            continue;
+        }
+
+        if is_code_block {
+            rv.push_str(line, STYLE_CODE);
+        }
+        else if let Some(text) = get_heading(trimmed_line) {
+            rv.push_str(text, STYLE_HEADING);
+        }
+        else if is_rule(trimmed_line) {
+            rv.push_str(RULE, STYLE_PLAIN);
+        }
+        else if let Some(text) = get_list_item(trimmed_line) {
+            // Keep the nesting
+            let indent = &line[..line.len()-trimmed_line.len()];
+            rv.push_str(indent, STYLE_PLAIN);
+            rv.push_str(BULLET, STYLE_PLAIN);
+            render_inline(text, STYLE_PLAIN, &mut rv);
+        }
+        else if let Some(text) = trimmed_line.strip_prefix('>') {
+            render_inline(text.trim_start(), STYLE_PLAIN, &mut rv);
+        }
+        else {
+            render_inline(line, STYLE_PLAIN, &mut rv);
+        }
+        rv.push('\n', STYLE_PLAIN);
+    }
+
+    return rv;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the style of the first occurrence of the text
+    fn get_style(rendered: &StyledText, text: &str) -> String {
+        let start = rendered.text.find(text).unwrap();
+        return rendered.style[start..start+text.len()].to_string();
+    }
+
+    #[test]
+    fn test_render_blocks() {
+        let rendered = render("# Changes\n\n- Fixed **crash** on start\n  * nested\n---\n```\nlet x = 1;\n```\n");
+
+        assert_eq!(
+            rendered.text,
+            format!("Changes\n\n{BULLET}Fixed crash on start\n  {BULLET}nested\n{RULE}\nlet x = 1;\n")
+        );
+        assert_eq!(rendered.text.len(), rendered.style.len());
+        assert_eq!(get_style(&rendered, "Changes"), "B".repeat(7));
+        assert_eq!(get_style(&rendered, "crash"), "C".repeat(5));
+        assert_eq!(get_style(&rendered, "Fixed"), "A".repeat(5));
+        assert_eq!(get_style(&rendered, "let x = 1;"), "D".repeat(10));
+    }
+
+    #[test]
+    fn test_render_inline() {
+        let rendered = render("Use `--proxy`, see [the wiki](https://example.com) ![logo](logo.png)");
+
+        assert_eq!(rendered.text, "Use --proxy, see the wiki logo\n");
+        assert_eq!(get_style(&rendered, "--proxy"), "D".repeat(7));
+        assert_eq!(get_style(&rendered, "the wiki"), "E".repeat(8));
+        assert_eq!(get_style(&rendered, "logo"), "E".repeat(4));
+    }
+
+    #[test]
+    fn test_render_unicode() {
+        let rendered = render("## 更新\n- **修复** 问题 * 和 `代码");
+
+        assert_eq!(rendered.text, format!("更新\n{BULLET}修复 问题 * 和 `代码\n"));
+        assert_eq!(rendered.text.len(), rendered.style.len());
+        assert_eq!(get_style(&rendered, "修复"), "C".repeat("修复".len()));
+    }
+}
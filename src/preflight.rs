@@ -0,0 +1,144 @@
+/// The module that implements pre-install checks of disk space and permissions
+
+use std::{
+    fmt,
+    path::{Path, PathBuf}
+};
+
+use crate::{
+    errors::InstallError,
+    utils
+};
+
+
+/// Result of a free space check
+#[derive(Debug, Clone)]
+pub struct SpaceCheck {
+    pub path: PathBuf,
+    pub required: u64,
+    /// None if we couldn't get the free space
+    pub available: Option<u64>
+}
+
+impl SpaceCheck {
+    /// Checks free space on the filesystem of the given path
+    pub fn new(path: &Path, required: u64) -> Self {
+        let path = get_existing_ancestor(path).to_path_buf();
+        let available = match fs2::available_space(&path) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                eprintln!("Failed to get free space for '{}': {e}", path.display());
+                None
+            }
+        };
+        return Self { path, required, available };
+    }
+
+    /// Returns true if there's enough space, or we couldn't tell
+    pub fn is_ok(&self) -> bool {
+        return self.available.map_or(true, |available| available >= self.required);
+    }
+}
+
+
+/// Result of a writability check
+#[derive(Debug, Clone)]
+pub struct WriteCheck {
+    pub path: PathBuf,
+    /// The error message if we couldn't write
+    pub error: Option<String>
+}
+
+impl WriteCheck {
+    /// Checks if we can create files in the given dir
+    pub fn new(path: &Path) -> Self {
+        let path = get_existing_ancestor(path).to_path_buf();
+        // The file is deleted on drop
+        let error = tempfile::Builder::new()
+            .prefix(".mas_installer-")
+            .tempfile_in(&path)
+            .err()
+            .map(|e| e.to_string());
+        return Self { path, error };
+    }
+
+    /// Returns true if we can write into the dir
+    pub fn is_ok(&self) -> bool {
+        return self.error.is_none();
+    }
+}
+
+
+/// Summary of all the pre-install checks we've done
+#[derive(Debug, Clone, Default)]
+pub struct PreflightReport {
+    pub space_checks: Vec<SpaceCheck>,
+    pub write_checks: Vec<WriteCheck>
+}
+
+impl PreflightReport {
+    /// Checks that the filesystem of the path has enough free space
+    pub fn check_space(&mut self, path: &Path, required: u64) {
+        self.space_checks.push(SpaceCheck::new(path, required));
+    }
+
+    /// Checks that we can write into the dir, does nothing if it was already checked
+    pub fn check_writable(&mut self, path: &Path) {
+        let path = get_existing_ancestor(path);
+        if !self.write_checks.iter().any(|c| c.path == path) {
+            self.write_checks.push(WriteCheck::new(path));
+        }
+    }
+
+    /// Returns true if any of the checks failed
+    pub fn has_problems(&self) -> bool {
+        return {
+            self.space_checks.iter().any(|c| !c.is_ok())
+            || self.write_checks.iter().any(|c| !c.is_ok())
+        };
+    }
+
+    /// Prints the summary and returns an error if any of the checks failed
+    pub fn verify(&self) -> Result<(), InstallError> {
+        println!("Pre-install checks:\n{self}");
+        if self.has_problems() {
+            return Err(InstallError::PreflightFailed(self.clone()));
+        }
+        return Ok(());
+    }
+}
+
+impl fmt::Display for PreflightReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in self.space_checks.iter() {
+            let available = match check.available {
+                Some(v) => utils::format_size(v),
+                None => "unknown".to_string()
+            };
+            writeln!(
+                f,
+                "[{}] '{}': {} required, {} available",
+                if check.is_ok() { "OK" } else { "FAIL" },
+                check.path.display(),
+                utils::format_size(check.required),
+                available
+            )?;
+        }
+        for check in self.write_checks.iter() {
+            match check.error {
+                Some(ref e) => writeln!(f, "[FAIL] '{}' isn't writable: {e}", check.path.display())?,
+                None => writeln!(f, "[OK] '{}' is writable", check.path.display())?
+            };
+        }
+        return Ok(());
+    }
+}
+
+
+/// Returns the closest ancestor of the path that exists (or the path itself)
+/// We may not have created the destination dirs yet
+fn get_existing_ancestor(path: &Path) -> &Path {
+    return path.ancestors()
+        .find(|p| p.exists())
+        .unwrap_or(path);
+}
@@ -0,0 +1,259 @@
+/// The module that implements rolling back the changes of an installation
+/// The journal records every file we write or remove, the files we remove or overwrite
+/// are moved into a backup dir next to the game, so we can put them back
+
+use std::{
+    collections::HashSet,
+    fs,
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex}
+};
+
+use tempfile::TempDir;
+
+
+/// Represents a change we can undo
+#[derive(Debug)]
+enum JournalEntry {
+    /// A file that didn't exist before
+    CreatedFile(PathBuf),
+    /// A dir that didn't exist before
+    CreatedDir(PathBuf),
+    /// A file or dir we've moved into the backup dir
+    MovedToBackup {
+        path: PathBuf,
+        backup_path: PathBuf
+    }
+}
+
+#[derive(Debug)]
+struct JournalData {
+    backup_root: PathBuf,
+    // Created on the first backup
+    backup_dir: Option<TempDir>,
+    entries: Vec<JournalEntry>,
+    // We only need the state before the first change of a path
+    known_paths: HashSet<PathBuf>
+}
+
+impl JournalData {
+    /// Moves the file or dir into the backup dir and records it
+    fn move_to_backup(&mut self, path: &Path) -> io::Result<()> {
+        if self.backup_dir.is_none() {
+            self.backup_dir = Some(
+                tempfile::Builder::new()
+                    .prefix(".mas_installer_backup-")
+                    .tempdir_in(&self.backup_root)?
+            );
+        }
+        // Safe to unwrap, we've just created it
+        let backup_path = self.backup_dir.as_ref().unwrap().path().join(self.entries.len().to_string());
+        move_path(path, &backup_path)?;
+
+        self.entries.push(
+            JournalEntry::MovedToBackup { path: path.to_path_buf(), backup_path }
+        );
+        return Ok(());
+    }
+}
+
+
+/// Moves a file or dir, falls back to copying files if we can't rename them
+/// (e.g. they're on different file systems)
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if let Err(e) = fs::rename(from, to) {
+        if !from.is_file() {
+            return Err(e);
+        }
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    return Ok(());
+}
+
+/// Puts a backed up file or dir back to its place, replaces what we've written there
+fn restore_path(path: &Path, backup_path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)?;
+    }
+    // The dir might have been removed after we've backed up the file
+    if let Some(parent_dir) = path.parent() {
+        fs::create_dir_all(parent_dir)?;
+    }
+    return move_path(backup_path, path);
+}
+
+
+/// Journal of the changes we've done in the game dir, the clones share the same journal
+#[derive(Debug, Clone)]
+pub struct FileJournal {
+    data: Arc<Mutex<JournalData>>
+}
+
+impl FileJournal {
+    /// Creates a new journal, the backups are kept in a temp dir inside backup_root
+    /// and removed once the journal is dropped
+    pub fn new(backup_root: &Path) -> Self {
+        let data = JournalData {
+            backup_root: backup_root.to_path_buf(),
+            backup_dir: None,
+            entries: Vec::new(),
+            known_paths: HashSet::new()
+        };
+        return Self { data: Arc::new(Mutex::new(data)) };
+    }
+
+    /// Checks if there's anything to roll back
+    pub fn has_changes(&self) -> bool {
+        return !self.data.lock().unwrap().entries.is_empty();
+    }
+
+    /// Records that we're about to write the file,
+    /// backs up the existing one so we can restore it
+    pub fn before_write(&self, path: &Path) -> io::Result<()> {
+        let mut data = self.data.lock().unwrap();
+        if !data.known_paths.insert(path.to_path_buf()) {
+            return Ok(());
+        }
+        if path.exists() {
+            return data.move_to_backup(path);
+        }
+        data.entries.push(JournalEntry::CreatedFile(path.to_path_buf()));
+        return Ok(());
+    }
+
+    /// Records the dirs we're about to create with create_dir_all
+    pub fn before_create_dir(&self, path: &Path) {
+        let mut data = self.data.lock().unwrap();
+        let missing_dirs: Vec<&Path> = path.ancestors()
+            .take_while(|dir| !dir.as_os_str().is_empty() && !dir.exists())
+            .collect();
+        // Parents go first, so we remove them last
+        for dir in missing_dirs.into_iter().rev() {
+            if data.known_paths.insert(dir.to_path_buf()) {
+                data.entries.push(JournalEntry::CreatedDir(dir.to_path_buf()));
+            }
+        }
+    }
+
+    /// Removes the file or dir by moving it into the backup dir
+    pub fn remove(&self, path: &Path) -> io::Result<()> {
+        let mut data = self.data.lock().unwrap();
+        // We've created it, no need to keep it
+        if data.known_paths.contains(path) {
+            return match path.is_dir() {
+                true => fs::remove_dir_all(path),
+                false => fs::remove_file(path)
+            };
+        }
+        data.move_to_backup(path)?;
+        data.known_paths.insert(path.to_path_buf());
+        return Ok(());
+    }
+
+    /// Undoes the recorded changes in reverse order
+    /// This is "best-effort", returns the paths we've failed to restore
+    pub fn rollback(&self) -> Vec<PathBuf> {
+        let mut data = self.data.lock().unwrap();
+        let mut failed_paths = Vec::new();
+
+        while let Some(entry) = data.entries.pop() {
+            let (path, rv) = match entry {
+                JournalEntry::CreatedFile(path) => {
+                    let rv = match fs::remove_file(&path) {
+                        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                        rv => rv
+                    };
+                    (path, rv)
+                },
+                JournalEntry::CreatedDir(path) => {
+                    // Keep the dirs with the files we didn't create
+                    let rv = match path.is_dir() && path.read_dir().map(|mut c| c.next().is_none()).unwrap_or(false) {
+                        true => fs::remove_dir(&path),
+                        false => Ok(())
+                    };
+                    (path, rv)
+                },
+                JournalEntry::MovedToBackup { path, backup_path } => {
+                    let rv = restore_path(&path, &backup_path);
+                    (path, rv)
+                }
+            };
+            if let Err(e) = rv {
+                eprintln!("Failed to restore '{}': {e}", path.display());
+                failed_paths.push(path);
+            }
+        }
+        data.known_paths.clear();
+
+        return failed_paths;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rollback_restores_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join("script.rpy"), "old script").unwrap();
+        fs::write(root.join("options.rpyc"), "old options").unwrap();
+
+        let journal = FileJournal::new(root);
+        assert!(!journal.has_changes());
+
+        // What prepare_ddlc_dir does
+        journal.remove(&root.join("options.rpyc")).unwrap();
+        assert!(!root.join("options.rpyc").exists());
+
+        // What the extraction does
+        let new_dir = root.join("game").join("mod_assets");
+        journal.before_create_dir(&new_dir);
+        fs::create_dir_all(&new_dir).unwrap();
+        journal.before_write(&new_dir.join("monika.png")).unwrap();
+        fs::write(new_dir.join("monika.png"), "new image").unwrap();
+        journal.before_write(&root.join("script.rpy")).unwrap();
+        fs::write(root.join("script.rpy"), "new script").unwrap();
+        // Writing the same file again keeps the original backup
+        journal.before_write(&root.join("script.rpy")).unwrap();
+        fs::write(root.join("script.rpy"), "newer script").unwrap();
+        assert!(journal.has_changes());
+
+        assert!(journal.rollback().is_empty());
+        assert!(!journal.has_changes());
+        assert_eq!(fs::read_to_string(root.join("script.rpy")).unwrap(), "old script");
+        assert_eq!(fs::read_to_string(root.join("options.rpyc")).unwrap(), "old options");
+        assert!(!root.join("game").exists());
+    }
+
+    #[test]
+    fn test_rollback_keeps_foreign_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let new_dir = temp_dir.path().join("game");
+
+        let journal = FileJournal::new(temp_dir.path());
+        journal.before_create_dir(&new_dir);
+        fs::create_dir_all(&new_dir).unwrap();
+        // Something we don't know about
+        fs::write(new_dir.join("user_file.txt"), "data").unwrap();
+
+        assert!(journal.rollback().is_empty());
+        assert!(new_dir.join("user_file.txt").exists());
+    }
+
+    #[test]
+    fn test_backup_dir_is_removed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("script.rpy"), "old script").unwrap();
+
+        let journal = FileJournal::new(temp_dir.path());
+        journal.remove(&temp_dir.path().join("script.rpy")).unwrap();
+        drop(journal);
+
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+    }
+}
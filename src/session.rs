@@ -0,0 +1,179 @@
+/// The module that implements the installation session
+/// The session keeps what we've done so far, so a failed installation
+/// can be retried from the step that failed instead of from the very beginning
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io,
+    path::{Path, PathBuf}
+};
+
+use tempfile::TempDir;
+
+use crate::rollback::FileJournal;
+
+
+/// Represents a temp file with the installer data
+/// We keep the path so the extraction threads can open their own handles
+#[derive(Debug)]
+pub struct TempFile {
+    pub file: File,
+    pub path: PathBuf
+}
+
+
+/// Represents a step of the installation we don't need to repeat once it's done
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum InstallStep {
+    PrepareDir,
+    Extract(String),
+    Stream(String),
+    Submods,
+    DesktopEntries
+}
+
+
+/// Represents the state of an installation into the given directory
+#[derive(Debug)]
+pub struct InstallSession {
+    ddlc_dir: PathBuf,
+    temp_dir: TempDir,
+    // Downloaded files by their urls, these can be incomplete
+    downloads: HashMap<String, TempFile>,
+    complete_downloads: HashSet<String>,
+    // The folders the user has chosen by component ids
+    chosen_folders: HashMap<String, Option<HashSet<String>>>,
+    done_steps: HashSet<InstallStep>,
+    // The changes we've done in the game dir
+    journal: FileJournal
+}
+
+impl InstallSession {
+    /// Creates a new session, the temp files are removed once it's dropped
+    pub fn new(ddlc_dir: &Path) -> Result<Self, io::Error> {
+        let temp_dir = tempfile::Builder::new()
+            .prefix(".mas_installer-")
+            .tempdir()?;
+
+        return Ok(
+            Self {
+                ddlc_dir: ddlc_dir.to_path_buf(),
+                temp_dir,
+                downloads: HashMap::new(),
+                complete_downloads: HashSet::new(),
+                chosen_folders: HashMap::new(),
+                done_steps: HashSet::new(),
+                journal: FileJournal::new(ddlc_dir)
+            }
+        );
+    }
+
+    /// Checks if this session installs into the given directory
+    pub fn is_for(&self, ddlc_dir: &Path) -> bool {
+        return self.ddlc_dir == ddlc_dir;
+    }
+
+    /// Returns the temp dir of the session
+    pub fn get_temp_dir(&self) -> &TempDir {
+        return &self.temp_dir;
+    }
+
+    /// Returns the file we download the given url into, if we've started it
+    pub fn get_download(&self, url: &str) -> Option<&TempFile> {
+        return self.downloads.get(url);
+    }
+
+    /// Returns the file we download the given url into as mutable, if we've started it
+    pub fn get_download_mut(&mut self, url: &str) -> Option<&mut TempFile> {
+        return self.downloads.get_mut(url);
+    }
+
+    /// Adds the file we download the given url into
+    pub fn add_download(&mut self, url: &str, temp_file: TempFile) {
+        self.complete_downloads.remove(url);
+        self.downloads.insert(url.to_string(), temp_file);
+    }
+
+    /// Checks if we've downloaded the given url completely
+    pub fn is_download_complete(&self, url: &str) -> bool {
+        return self.complete_downloads.contains(url);
+    }
+
+    /// Marks the download of the given url as complete
+    pub fn set_download_complete(&mut self, url: &str) {
+        self.complete_downloads.insert(url.to_string());
+    }
+
+    /// Returns the folders the user has chosen for the component,
+    /// None if we haven't asked yet
+    pub fn get_chosen_folders(&self, component_id: &str) -> Option<&Option<HashSet<String>>> {
+        return self.chosen_folders.get(component_id);
+    }
+
+    /// Sets the folders the user has chosen for the component
+    pub fn set_chosen_folders(&mut self, component_id: &str, folders: Option<HashSet<String>>) {
+        self.chosen_folders.insert(component_id.to_string(), folders);
+    }
+
+    /// Checks if the step is done
+    pub fn is_step_done(&self, step: &InstallStep) -> bool {
+        return self.done_steps.contains(step);
+    }
+
+    /// Marks the step as done
+    pub fn set_step_done(&mut self, step: InstallStep) {
+        self.done_steps.insert(step);
+    }
+
+    /// Returns the journal of the changes we've done in the game dir
+    pub fn get_journal(&self) -> &FileJournal {
+        return &self.journal;
+    }
+
+    /// Forgets the steps and the folder choices, but keeps the downloads and the journal
+    /// Used when the user goes back to change the options
+    pub fn reset_steps(&mut self) {
+        self.chosen_folders.clear();
+        self.done_steps.clear();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reset_steps_keeps_downloads() {
+        let ddlc_dir = Path::new("ddlc");
+        let mut session = InstallSession::new(ddlc_dir).unwrap();
+        assert!(session.is_for(ddlc_dir));
+        assert!(!session.is_for(Path::new("other")));
+
+        let path = session.get_temp_dir().path().join("mas.tmp");
+        let file = File::create(&path).unwrap();
+        session.add_download("url", TempFile { file, path });
+        session.set_download_complete("url");
+        session.set_chosen_folders("spritepacks", None);
+        session.set_step_done(InstallStep::PrepareDir);
+        session.set_step_done(InstallStep::Extract("mas".to_string()));
+
+        session.reset_steps();
+        assert!(session.is_download_complete("url"));
+        assert!(session.get_download("url").is_some());
+        assert!(session.get_chosen_folders("spritepacks").is_none());
+        assert!(!session.is_step_done(&InstallStep::PrepareDir));
+        assert!(!session.is_step_done(&InstallStep::Extract("mas".to_string())));
+    }
+
+    #[test]
+    fn test_temp_dir_is_removed() {
+        let session = InstallSession::new(Path::new("ddlc")).unwrap();
+        let temp_dir = session.get_temp_dir().path().to_path_buf();
+        assert!(temp_dir.is_dir());
+
+        drop(session);
+        assert!(!temp_dir.exists());
+    }
+}
@@ -0,0 +1,238 @@
+/// The module that implements validation of archives we extract while downloading
+/// We can't seek in a download stream, so the entries are read from their local headers
+/// and the central directory is only checked at the very end
+
+use zip::{
+    result::ZipError,
+    read::ZipFile
+};
+
+use crate::errors::ExtractionError;
+
+
+// Signature of a central directory file header
+pub const CENTRAL_DIR_HEADER_SIGNATURE: u32 = 0x02014b50;
+// Size of a central directory file header without the variable fields
+const CENTRAL_DIR_HEADER_SIZE: usize = 46;
+// Id of the extra field with 64-bit sizes
+const ZIP64_EXTRA_FIELD_ID: u16 = 0x0001;
+// Value of 32-bit fields that were moved into the zip64 extra field
+const ZIP64_MARKER: u32 = 0xFFFFFFFF;
+// Value of the upper byte of "version made by" for unix archives
+const UNIX_SYSTEM: u16 = 3;
+// We don't read central directories larger than this
+pub const MAX_CENTRAL_DIR_SIZE: u64 = 64*1024*1024;
+
+
+/// Represents the metadata of an archive entry we compare between
+/// the local headers and the central directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryInfo {
+    pub name_raw: Vec<u8>,
+    pub crc32: u32,
+    pub compressed_size: u64,
+    pub size: u64
+}
+
+impl EntryInfo {
+    /// Creates entry info from a file we've read from the stream
+    pub fn from_zip_file(file: &ZipFile) -> Self {
+        return Self {
+            name_raw: file.name_raw().to_vec(),
+            crc32: file.crc32(),
+            compressed_size: file.compressed_size(),
+            size: file.size()
+        };
+    }
+}
+
+
+/// Represents an entry of the central directory
+#[derive(Debug, Clone)]
+pub struct CentralDirEntry {
+    pub info: EntryInfo,
+    /// Unix permissions, they're only stored in the central directory
+    pub unix_mode: Option<u32>
+}
+
+
+/// Returns the error we raise for broken central directories
+fn invalid_central_dir() -> ExtractionError {
+    return ExtractionError::ArchiveError(ZipError::InvalidArchive("invalid central directory"));
+}
+
+/// Reads a little-endian u16 at the offset
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset+2)?;
+    return Some(u16::from_le_bytes([bytes[0], bytes[1]]));
+}
+
+/// Reads a little-endian u32 at the offset
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset+4)?;
+    return Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+}
+
+/// Reads a little-endian u64 at the offset
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let low = read_u32(data, offset)? as u64;
+    let high = read_u32(data, offset+4)? as u64;
+    return Some(high << 32 | low);
+}
+
+/// Reads the 64-bit sizes from the extra field if the 32-bit ones are overflown
+/// Returns the (compressed size, size) pair
+fn read_zip64_sizes(extra: &[u8], compressed_size: u32, size: u32) -> Option<(u64, u64)> {
+    let mut compressed_size = compressed_size as u64;
+    let mut size = size as u64;
+
+    let mut offset = 0;
+    while offset + 4 <= extra.len() {
+        let field_id = read_u16(extra, offset)?;
+        let field_len = read_u16(extra, offset+2)? as usize;
+        let field = extra.get(offset+4..offset+4+field_len)?;
+
+        if field_id == ZIP64_EXTRA_FIELD_ID {
+            // The fields are only present if their 32-bit versions are overflown
+            let mut field_offset = 0;
+            if size == ZIP64_MARKER as u64 {
+                size = read_u64(field, field_offset)?;
+                field_offset += 8;
+            }
+            if compressed_size == ZIP64_MARKER as u64 {
+                compressed_size = read_u64(field, field_offset)?;
+            }
+            break;
+        }
+        offset += 4 + field_len;
+    }
+
+    return Some((compressed_size, size));
+}
+
+/// Parses the central directory
+/// data must start at the first central directory header,
+/// what goes after the last header (end of central directory records) is ignored
+pub fn parse_central_dir(data: &[u8]) -> Result<Vec<CentralDirEntry>, ExtractionError> {
+    let mut entries = Vec::new();
+
+    let mut offset = 0;
+    while read_u32(data, offset) == Some(CENTRAL_DIR_HEADER_SIGNATURE) {
+        let header = data.get(offset..offset+CENTRAL_DIR_HEADER_SIZE)
+            .ok_or_else(invalid_central_dir)?;
+        // Safe to unwrap, we've checked the header size
+        let version_made_by = read_u16(header, 4).unwrap();
+        let crc32 = read_u32(header, 16).unwrap();
+        let compressed_size = read_u32(header, 20).unwrap();
+        let size = read_u32(header, 24).unwrap();
+        let name_len = read_u16(header, 28).unwrap() as usize;
+        let extra_len = read_u16(header, 30).unwrap() as usize;
+        let comment_len = read_u16(header, 32).unwrap() as usize;
+        let external_attributes = read_u32(header, 38).unwrap();
+
+        let name_start = offset + CENTRAL_DIR_HEADER_SIZE;
+        let extra_start = name_start + name_len;
+        let name_raw = data.get(name_start..extra_start)
+            .ok_or_else(invalid_central_dir)?;
+        let extra = data.get(extra_start..extra_start+extra_len)
+            .ok_or_else(invalid_central_dir)?;
+
+        let (compressed_size, size) = read_zip64_sizes(extra, compressed_size, size)
+            .ok_or_else(invalid_central_dir)?;
+
+        let unix_mode = match version_made_by >> 8 == UNIX_SYSTEM && external_attributes != 0 {
+            true => Some(external_attributes >> 16),
+            false => None
+        };
+
+        entries.push(
+            CentralDirEntry {
+                info: EntryInfo {
+                    name_raw: name_raw.to_vec(),
+                    crc32,
+                    compressed_size,
+                    size
+                },
+                unix_mode
+            }
+        );
+        offset = extra_start + extra_len + comment_len;
+    }
+
+    return Ok(entries);
+}
+
+/// Checks that the entries we've read from the stream match the central directory
+pub fn validate_entries(streamed: &[EntryInfo], central_dir: &[CentralDirEntry]) -> Result<(), ExtractionError> {
+    if streamed.len() != central_dir.len() {
+        return Err(ExtractionError::ArchiveError(
+            ZipError::InvalidArchive("central directory has a different number of entries")
+        ));
+    }
+    for (entry, central_entry) in streamed.iter().zip(central_dir.iter()) {
+        if *entry != central_entry.info {
+            return Err(ExtractionError::ArchiveError(
+                ZipError::InvalidArchive("central directory doesn't match the local headers")
+            ));
+        }
+    }
+
+    return Ok(());
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read};
+    use zip::read::read_zipfile_from_stream;
+    use crate::installer::tests::build_archive;
+
+    /// Reads all the entries from the stream, returns their info and the rest of the stream
+    fn read_stream(data: &[u8]) -> (Vec<EntryInfo>, Vec<u8>) {
+        let mut reader = Cursor::new(data);
+        let mut entries = Vec::new();
+        while let Some(mut file) = read_zipfile_from_stream(&mut reader).unwrap() {
+            std::io::copy(&mut file, &mut std::io::sink()).unwrap();
+            entries.push(EntryInfo::from_zip_file(&file));
+        }
+
+        let mut central_dir = CENTRAL_DIR_HEADER_SIGNATURE.to_le_bytes().to_vec();
+        reader.read_to_end(&mut central_dir).unwrap();
+
+        return (entries, central_dir);
+    }
+
+    #[test]
+    fn test_central_dir_matches_stream() {
+        let data = build_archive(&[("DDLC.sh", 0o755), ("game/script.rpy", 0o644)]);
+        let (entries, central_dir) = read_stream(&data);
+        let central_entries = parse_central_dir(&central_dir).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert!(validate_entries(&entries, &central_entries).is_ok());
+        assert_eq!(central_entries[0].info.name_raw, b"DDLC.sh");
+        assert_eq!(central_entries[0].unix_mode.map(|m| m & 0o777), Some(0o755));
+        assert_eq!(central_entries[1].unix_mode.map(|m| m & 0o777), Some(0o644));
+    }
+
+    #[test]
+    fn test_central_dir_mismatch() {
+        let data = build_archive(&[("game/a.rpy", 0o644), ("game/b.rpy", 0o644)]);
+        let (mut entries, central_dir) = read_stream(&data);
+        let central_entries = parse_central_dir(&central_dir).unwrap();
+
+        entries[1].crc32 ^= 1;
+        assert!(validate_entries(&entries, &central_entries).is_err());
+        entries.pop();
+        assert!(validate_entries(&entries, &central_entries).is_err());
+    }
+
+    #[test]
+    fn test_truncated_central_dir() {
+        let data = build_archive(&[("game/a.rpy", 0o644)]);
+        let (_, central_dir) = read_stream(&data);
+
+        assert!(parse_central_dir(&central_dir[..CENTRAL_DIR_HEADER_SIZE+2]).is_err());
+    }
+}
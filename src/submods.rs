@@ -0,0 +1,276 @@
+/// The module that implements the submods catalog and tracking of installed submods
+
+use std::{
+    cmp::Ordering,
+    fs,
+    path::{Path, PathBuf},
+    thread
+};
+
+use fltk::app::Sender;
+use ::reqwest::blocking as reqwest;
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    app::{
+        state::ThreadSafeState,
+        Message
+    },
+    cache,
+    errors::InstallError,
+    installer,
+    rollback::FileJournal,
+    utils
+};
+
+
+// Name of the cache entry with the catalog
+const CATALOG_CACHE_NAME: &str = "submods_catalog";
+// Where we keep the record of installed submods, relative to the game dir
+const RECORD_FILE_PATH: &str = "game/Submods/mas_installer_submods.json";
+// Where submods are installed, relative to the game dir
+pub const SUBMODS_DIR_PATH: &str = "game/Submods";
+
+
+/// Represents a submod in the catalog
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubmodInfo {
+    /// Unique id of the submod, also used as a file name
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// GitHub repo in the owner/name format
+    pub repo: String,
+    /// Regex pattern for the release asset, by default the first zip is used
+    #[serde(default)]
+    pub asset_pattern: Option<String>,
+    #[serde(default)]
+    pub min_mas_version: Option<String>,
+    #[serde(default)]
+    pub max_mas_version: Option<String>
+}
+
+impl SubmodInfo {
+    /// Check if this submod is valid
+    pub fn is_valid(&self) -> bool {
+        let is_id_safe = self.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        let is_repo_valid = match self.repo.split_once('/') {
+            Some((owner, name)) => !owner.is_empty() && !name.is_empty() && !name.contains('/'),
+            None => false
+        };
+
+        return {
+            !self.id.is_empty()
+            && is_id_safe
+            && !self.name.is_empty()
+            && is_repo_valid
+        };
+    }
+
+    /// Checks if this submod supports the given MAS version
+    /// If the version is unknown, we assume it's compatible
+    pub fn is_compatible(&self, mas_version: Option<&str>) -> bool {
+        let mas_version = match mas_version {
+            Some(v) => v,
+            None => return true
+        };
+        if let Some(ref min_ver) = self.min_mas_version {
+            if utils::compare_versions(mas_version, min_ver) == Ordering::Less {
+                return false;
+            }
+        }
+        if let Some(ref max_ver) = self.max_mas_version {
+            if utils::compare_versions(mas_version, max_ver) == Ordering::Greater {
+                return false;
+            }
+        }
+        return true;
+    }
+}
+
+
+/// Represents the submods catalog
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SubmodsCatalog {
+    pub submods: Vec<SubmodInfo>
+}
+
+impl SubmodsCatalog {
+    /// Returns the submod with the given id
+    pub fn get_submod(&self, id: &str) -> Option<&SubmodInfo> {
+        return self.submods.iter().find(|s| s.id == id);
+    }
+}
+
+
+/// Represents a submod installed by us
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstalledSubmod {
+    pub id: String,
+    /// Tag of the release we installed
+    pub version: String,
+    /// Installed files relative to the game dir
+    pub files: Vec<PathBuf>
+}
+
+
+/// Represents the record of the submods we installed
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SubmodsRecord {
+    pub submods: Vec<InstalledSubmod>
+}
+
+impl SubmodsRecord {
+    /// Loads the record from the given game dir
+    /// If there's no record, returns an empty one
+    pub fn load(game_dir: &Path) -> Self {
+        let data = match fs::read(Self::get_path(game_dir)) {
+            Ok(data) => data,
+            Err(_) => return Self::default()
+        };
+        return match serde_json::from_slice(&data) {
+            Ok(record) => record,
+            Err(e) => {
+                eprintln!("Submods record is invalid: {e}");
+                Self::default()
+            }
+        };
+    }
+
+    /// Returns the path of the record in the given game dir
+    pub fn get_path(game_dir: &Path) -> PathBuf {
+        return game_dir.join(RECORD_FILE_PATH);
+    }
+
+    /// Saves the record into the given game dir
+    pub fn save(&self, game_dir: &Path, journal: &FileJournal) -> Result<(), InstallError> {
+        let path = Self::get_path(game_dir);
+        if let Some(parent_dir) = path.parent() {
+            journal.before_create_dir(parent_dir);
+            fs::create_dir_all(parent_dir)?;
+        }
+        journal.before_write(&path)?;
+        fs::write(&path, serde_json::to_vec_pretty(self)?)?;
+
+        return Ok(());
+    }
+
+    /// Returns the installed submod with the given id
+    pub fn get(&self, id: &str) -> Option<&InstalledSubmod> {
+        return self.submods.iter().find(|s| s.id == id);
+    }
+
+    /// Adds a submod to the record, replaces the old entry if any
+    pub fn insert(&mut self, submod: InstalledSubmod) {
+        self.remove(&submod.id);
+        self.submods.push(submod);
+    }
+
+    /// Removes a submod from the record
+    pub fn remove(&mut self, id: &str) -> Option<InstalledSubmod> {
+        let i = self.submods.iter().position(|s| s.id == id)?;
+        return Some(self.submods.remove(i));
+    }
+}
+
+
+/// Removes files of an installed submod
+/// This function is "best-effort" and will ignore errors
+/// keep_files are the files that shouldn't be removed (e.g. the new version has them)
+/// The files are removed via the journal, so we can restore them
+pub fn remove_submod_files(game_dir: &Path, submod: &InstalledSubmod, keep_files: &[PathBuf], journal: &FileJournal) {
+    let submods_dir = game_dir.join(SUBMODS_DIR_PATH);
+
+    for file in submod.files.iter() {
+        if keep_files.contains(file) {
+            continue;
+        }
+        // Sanity check, the record might have been edited
+        if file.is_absolute() || file.components().any(|c| c.as_os_str() == "..") {
+            eprintln!("Skipping unsafe path '{}'", file.display());
+            continue;
+        }
+        let path = game_dir.join(file);
+        if path.is_file() && journal.remove(&path).is_err() {
+            eprintln!("Failed to delete '{}'", path.display());
+        }
+        // Remove the dirs left empty, but never the Submods dir itself
+        let mut parent_dir = path.parent();
+        while let Some(dir) = parent_dir {
+            if dir == submods_dir || !dir.starts_with(&submods_dir) || fs::remove_dir(dir).is_err() {
+                break;
+            }
+            parent_dir = dir.parent();
+        }
+    }
+}
+
+
+/// Requests the catalog
+fn fetch_catalog(client: &reqwest::Client, url: &str) -> Result<SubmodsCatalog, InstallError> {
+    let resp = client.get(url).send()?;
+    let status_code = resp.status();
+    if !status_code.is_success() {
+        return Err(InstallError::ApiError(status_code, "failed to get submods catalog".to_string()));
+    }
+
+    let mut catalog: SubmodsCatalog = serde_json::from_slice(&resp.bytes()?)?;
+    catalog.submods.retain(
+        |s| {
+            if !s.is_valid() {
+                eprintln!("Submod '{}' is invalid", s.id);
+            }
+            s.is_valid()
+        }
+    );
+
+    return Ok(catalog);
+}
+
+/// Requests the catalog, falls back to the cache if we can't access it
+fn get_catalog(client: &reqwest::Client, url: &str) -> Result<SubmodsCatalog, InstallError> {
+    return match fetch_catalog(client, url) {
+        Ok(catalog) => {
+            cache::save(CATALOG_CACHE_NAME, &catalog);
+            Ok(catalog)
+        },
+        Err(e) => {
+            match cache::load::<SubmodsCatalog>(CATALOG_CACHE_NAME) {
+                Some(catalog) => {
+                    eprintln!("Failed to fetch submods catalog, using cache: {e}");
+                    Ok(catalog)
+                },
+                None => Err(e)
+            }
+        }
+    };
+}
+
+/// Requests the catalog in a separate thread, on success the catalog is saved into the app state
+/// The SubmodsCatalogLoaded message is sent either way, unless there's no catalog URL
+pub fn fetch_catalog_in_thread(
+    sender: Sender<Message>,
+    app_state: &ThreadSafeState
+) -> thread::JoinHandle<()> {
+    let app_state = app_state.clone();
+
+    return thread::spawn(
+        move || {
+            let config = app_state.lock().unwrap().get_config().clone();
+            let url = match config.get_submods_catalog_url() {
+                Some(url) => url,
+                None => return
+            };
+            let rv = installer::build_client(&config)
+                .and_then(|client| get_catalog(&client, url));
+            match rv {
+                Ok(catalog) => {
+                    app_state.lock().unwrap().set_submods_catalog(Some(catalog));
+                },
+                Err(e) => eprintln!("Failed to fetch submods catalog: {e}")
+            };
+            sender.send(Message::SubmodsCatalogLoaded);
+        }
+    );
+}
@@ -0,0 +1,178 @@
+/// The module that implements the summary of the installation
+/// we show to the user before starting it
+
+use std::{
+    fmt,
+    path::{Path, PathBuf}
+};
+
+use crate::{
+    app::state::AppState,
+    components::get_game_dir,
+    desktop_entry,
+    installer::get_install_removals,
+    preflight::SpaceCheck,
+    utils::{self, format_size}
+};
+
+
+// We don't list more files than this
+const MAX_LISTED_FILES: usize = 20;
+
+
+/// Represents a component we're going to install
+#[derive(Debug, Clone)]
+pub struct ComponentSummary {
+    pub name: String,
+    /// None if we don't have the release data yet
+    pub download_size: Option<u64>,
+    /// If we extract it while downloading
+    pub is_streamed: bool
+}
+
+
+/// Represents what the installation is going to do
+#[derive(Debug, Clone)]
+pub struct InstallSummary {
+    pub ddlc_dir: PathBuf,
+    pub is_ddlc_dir: bool,
+    pub is_mas_installed: bool,
+    /// The version and the name of the release
+    pub release: Option<(String, String)>,
+    pub components: Vec<ComponentSummary>,
+    pub installed_submods: Vec<String>,
+    pub removed_submods: Vec<String>,
+    pub removed_files: Vec<PathBuf>,
+    /// The desktop entries we're going to create
+    pub desktop_entries: Vec<PathBuf>,
+    pub space_check: SpaceCheck
+}
+
+impl InstallSummary {
+    /// Collects the summary from the app state
+    pub fn new(app_state: &AppState) -> Self {
+        let ddlc_dir = app_state.get_extraction_dir().clone();
+        let game_dir = get_game_dir(&ddlc_dir);
+        let release_data = app_state.get_release_data();
+        let streaming_install = app_state.get_config().streaming_install;
+        let (menu_entry, desktop_shortcut) = app_state.get_desktop_entry_flags();
+
+        let components: Vec<ComponentSummary> = app_state.get_components()
+            .iter()
+            .filter(|c| app_state.is_component_selected(&c.id))
+            .map(
+                |c| ComponentSummary {
+                    name: c.display_name.clone(),
+                    download_size: release_data.and_then(|d| d.get_asset(&c.id)).map(|a| a.get_size()),
+                    is_streamed: streaming_install && !c.selective
+                }
+            )
+            .collect();
+
+        let selected_submods = app_state.get_selected_submods();
+        let installed_submods = app_state.get_installed_submods();
+        let (installed, removed): (Vec<_>, Vec<_>) = app_state.get_available_submods()
+            .into_iter()
+            .filter(|s| selected_submods.contains(&s.id) || installed_submods.get(&s.id).is_some())
+            .partition(|s| selected_submods.contains(&s.id));
+
+        // The extracted files take at least as much as the archives
+        let install_size = components.iter().filter_map(|c| c.download_size).sum();
+
+        return Self {
+            is_ddlc_dir: utils::is_valid_ddlc_dir(&ddlc_dir),
+            is_mas_installed: utils::is_mas_installed(&game_dir),
+            release: release_data.map(|d| (d.get_version().to_string(), d.get_name().to_string())),
+            components,
+            installed_submods: installed.into_iter().map(|s| s.name.clone()).collect(),
+            removed_submods: removed.into_iter().map(|s| s.name.clone()).collect(),
+            removed_files: get_install_removals(&game_dir),
+            desktop_entries: desktop_entry::get_entry_paths(menu_entry, desktop_shortcut),
+            space_check: SpaceCheck::new(&game_dir, install_size),
+            ddlc_dir
+        };
+    }
+
+    /// Returns the size of the temp files we download
+    pub fn get_download_size(&self) -> u64 {
+        return self.components.iter()
+            .filter(|c| !c.is_streamed)
+            .filter_map(|c| c.download_size)
+            .sum();
+    }
+
+    /// Returns the path relative to the DDLC dir if we can
+    fn get_display_path<'a>(&self, path: &'a Path) -> &'a Path {
+        return path.strip_prefix(&self.ddlc_dir).unwrap_or(path);
+    }
+}
+
+impl fmt::Display for InstallSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "目标文件夹: {}", self.ddlc_dir.display())?;
+        match self.is_ddlc_dir {
+            true => writeln!(f, "DDLC: 已找到")?,
+            false => writeln!(f, "DDLC: 未找到 (这个文件夹看起来不是DDLC的文件夹)")?
+        };
+        match self.is_mas_installed {
+            true => writeln!(f, "MAS: 已安装, 将被更新")?,
+            false => writeln!(f, "MAS: 未安装")?
+        };
+        match self.release {
+            Some((ref version, ref name)) => writeln!(f, "版本: {version} ({name})")?,
+            None => writeln!(f, "版本: 未知, 将在安装时获取")?
+        };
+
+        writeln!(f, "\n安装项:")?;
+        for component in self.components.iter() {
+            match component.download_size {
+                Some(size) => writeln!(f, "    {} - {}", component.name, format_size(size))?,
+                None => writeln!(f, "    {}", component.name)?
+            };
+        }
+
+        if !self.installed_submods.is_empty() || !self.removed_submods.is_empty() {
+            writeln!(f, "\n子模组:")?;
+            for name in self.installed_submods.iter() {
+                writeln!(f, "    安装/更新 {name}")?;
+            }
+            for name in self.removed_submods.iter() {
+                writeln!(f, "    移除 {name}")?;
+            }
+        }
+
+        if !self.removed_files.is_empty() {
+            writeln!(f, "\n将被删除的文件 ({}):", self.removed_files.len())?;
+            for path in self.removed_files.iter().take(MAX_LISTED_FILES) {
+                writeln!(f, "    {}", self.get_display_path(path).display())?;
+            }
+            if self.removed_files.len() > MAX_LISTED_FILES {
+                writeln!(f, "    ...还有 {} 个文件", self.removed_files.len() - MAX_LISTED_FILES)?;
+            }
+        }
+
+        if !self.desktop_entries.is_empty() {
+            writeln!(f, "\n快捷方式:")?;
+            for path in self.desktop_entries.iter() {
+                writeln!(f, "    {}", path.display())?;
+            }
+        }
+
+        writeln!(f, "\n磁盘占用 (估计):")?;
+        writeln!(f, "    临时文件: {}", format_size(self.get_download_size()))?;
+        write!(f, "    安装后: 至少 {}", format_size(self.space_check.required))?;
+        match self.space_check.available {
+            Some(available) => writeln!(f, " (可用空间: {})", format_size(available))?,
+            None => writeln!(f)?
+        };
+        // We don't know the size of the files we extract while downloading, only of their archives
+        if self.components.iter().any(|c| c.is_streamed) {
+            writeln!(f, "    边下载边解压的安装项按下载大小估计, 实际占用会更多")?;
+        }
+        if !self.space_check.is_ok() {
+            writeln!(f, "    注意! 可用空间可能不足")?;
+        }
+
+        return Ok(());
+    }
+}
@@ -1,8 +1,11 @@
 /// Module with utils functions
 
 use std::{
+    cmp::Ordering,
     env,
-    path::PathBuf,
+    io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
     fs::read_dir
 };
 
@@ -31,6 +34,67 @@ pub fn get_cwd() -> PathBuf {
     return cwd.ok().unwrap_or_default();
 }
 
+/// Compares 2 version strings like v0.12.9 and 0.12.10
+/// Missing parts are treated as 0, non-numeric suffixes are ignored
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    fn parse(v: &str) -> Vec<u64> {
+        return v.trim()
+            .trim_start_matches(['v', 'V'])
+            .split('.')
+            .map(|part| {
+                let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+                digits.parse::<u64>().unwrap_or_default()
+            })
+            .collect();
+    }
+
+    let a = parse(a);
+    let b = parse(b);
+    for i in 0..a.len().max(b.len()) {
+        let ord = a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0));
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    return Ordering::Equal;
+}
+
+/// Formats the size in bytes into a human-readable string
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if size < 1024 {
+        return format!("{size} B");
+    }
+    let mut size = size as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next_unit in UNITS.iter().skip(1) {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next_unit;
+    }
+    return format!("{size:.1} {unit}");
+}
+
+/// Returns the directory for the installer cache
+/// None if we couldn't find a suitable location
+pub fn get_cache_dir() -> Option<PathBuf> {
+    let home_dir = env::var_os("HOME").map(PathBuf::from);
+    let base_dir = match env::consts::OS {
+        "windows" => env::var_os("LOCALAPPDATA").map(PathBuf::from),
+        "macos" => home_dir.map(|p| p.join("Library/Caches")),
+        _ => {
+            env::var_os("XDG_CACHE_HOME")
+                .map(PathBuf::from)
+                .filter(|p| p.is_absolute())
+                .or_else(|| home_dir.map(|p| p.join(".cache")))
+        }
+    }?;
+    return Some(base_dir.join("mas-installer"));
+}
+
 /// Checks if the given path is a valid DDLC directory
 pub fn is_valid_ddlc_dir(path: &PathBuf) -> bool {
     const TOTAL_CONDITIONS: u16 = 5;
@@ -98,3 +162,45 @@ pub fn is_valid_ddlc_dir(path: &PathBuf) -> bool {
 
     return flag == REQUIRED_FLAG;
 }
+
+/// Checks if MAS is installed in the given game dir
+pub fn is_mas_installed(game_dir: &Path) -> bool {
+    let scripts_dir = game_dir.join("game");
+    return scripts_dir.join("definitions.rpyc").is_file() || scripts_dir.join("definitions.rpy").is_file();
+}
+
+/// Launches the game from the given DDLC directory as a detached process
+/// NOTE: on mac the directory is the app bundle itself
+pub fn launch_game(ddlc_dir: &Path) -> io::Result<()> {
+    let mut cmd = match env::consts::OS {
+        "macos" => {
+            let mut cmd = Command::new("open");
+            cmd.arg(ddlc_dir);
+            cmd
+        },
+        os => {
+            let launcher = ddlc_dir.join(if os == "windows" { "DDLC.exe" } else { "DDLC.sh" });
+            if !launcher.is_file() {
+                return Err(
+                    io::Error::new(io::ErrorKind::NotFound, format!("'{}' doesn't exist", launcher.display()))
+                );
+            }
+            Command::new(launcher)
+        }
+    };
+    cmd.current_dir(ddlc_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        // DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP
+        cmd.creation_flags(0x00000008 | 0x00000200);
+    }
+
+    // We don't wait for the game, it keeps running after we exit
+    cmd.spawn()?;
+    return Ok(());
+}